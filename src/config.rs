@@ -0,0 +1,168 @@
+use tetra::graphics::Color;
+
+pub const SETTINGS_PATH: &str = "./config.toml";
+// Physics steps per second; drawing runs every frame in between.
+pub const TICK_RATE: f64 = 60.0;
+pub const SAVE_PATH: &str = "./savegame.json";
+pub const REPLAY_PATH: &str = "./replay.json";
+pub const TOAST_DURATION: f32 = 2.0;
+// Older toasts are dropped past this many, so a burst can't back up for long.
+pub const TOAST_QUEUE_LIMIT: usize = 4;
+pub const WINDOW_WIDTH: f32 = 1920.0;
+pub const WINDOW_HEIGHT: f32 = 1080.0;
+pub const PADDLE_SPEED: f32 = 480.0;
+// Player-steered paddles ramp up to their speed while a direction is held and
+// coast to a stop when it's released, in pixels per second squared.
+pub const PADDLE_ACCELERATION: f32 = 3600.0;
+pub const PADDLE_DECELERATION: f32 = 2400.0;
+// A dash multiplies a player's top speed for a moment, then needs to recharge.
+pub const DASH_DURATION: f32 = 0.15;
+pub const DASH_MULTIPLIER: f32 = 2.0;
+pub const DASH_COOLDOWN: f32 = 1.5;
+pub const DASH_METER_THICKNESS: f32 = 4.0;
+pub const BALL_SPEED: f32 = 600.0;
+pub const PADDLE_SPIN: f32 = 240.0;
+// Exponential decay rate of the ball's vertical speed, per second, so it
+// straightens out between hits. Zero keeps spin until the next hit.
+pub const SPIN_DECAY: f32 = 0.15;
+// Downward acceleration on the ball, in pixels per second squared, when the
+// gravity option is on.
+pub const GRAVITY: f32 = 400.0;
+// A spinning ball's path turns at up to this many radians per second (at full
+// spin), and the spin itself fades by MAGNUS_DECAY per second.
+pub const MAGNUS_STRENGTH: f32 = 1.2;
+pub const MAGNUS_DECAY: f32 = 0.8;
+// Spin from where the ball met the paddle (per unit of `hit_offset`) and
+// from the paddle's own motion (per unit of PADDLE_SPEED).
+pub const HIT_OFFSET_SPIN: f32 = 1.0;
+pub const PADDLE_MOTION_SPIN: f32 = 0.5;
+pub const BALL_ACC: f32 = 30.0;
+pub const MAX_BALL_SPEED: f32 = 1500.0;
+pub const SWEET_SPOT_SPEEDUP: f32 = 1.15;
+pub const SWEET_SPOT_STRAIGHTEN: f32 = 0.5;
+pub const EDGE_SPEED_FACTOR: f32 = 0.9;
+pub const EDGE_SPIN_BONUS: f32 = 0.75;
+// Hits are measured up to the paddle's ends; a ball clipping a corner counts
+// as an end hit rather than spinning off harder.
+pub const MAX_HIT_OFFSET: f32 = 0.5;
+// Degrees from horizontal a returned ball may travel at, so every return
+// still makes headway towards the other side.
+pub const MAX_BOUNCE_ANGLE: f32 = 60.0;
+pub const SERVE_MAX_ANGLE: f32 = 20.0;
+pub const ATTRACT_IDLE_TIME: f32 = 30.0;
+pub const AI_KP: f32 = 6.0;
+pub const AI_KI: f32 = 0.5;
+pub const AI_KD: f32 = 0.15;
+pub const AI_INTEGRAL_LIMIT: f32 = 200.0;
+pub const SERVE_CHARGE_TIME: f32 = 1.0;
+pub const SERVE_MAX_POWER: f32 = 1.6;
+pub const CHARGE_METER_WIDTH: f32 = 80.0;
+pub const CHARGE_METER_HEIGHT: f32 = 8.0;
+pub const WIN_SCORE: u32 = 11;
+// Ranges and steps for the options screen.
+// A game is won by two clear points, so a win score of 1 can't be played to.
+pub const MIN_WIN_SCORE: u32 = 2;
+pub const MAX_WIN_SCORE: u32 = 21;
+pub const MIN_OPTION_BALL_SPEED: f32 = 200.0;
+pub const BALL_SPEED_STEP: f32 = 50.0;
+pub const VOLUME_STEP: f32 = 0.1;
+pub const MATCH_DURATION: f32 = 120.0;
+// Best-of series: the first player to win more than half of these games takes it.
+pub const SERIES_LENGTH: u32 = 3;
+pub const NET_WIDTH: f32 = 8.0;
+pub const HIGH_CONTRAST_NET_WIDTH: f32 = 16.0;
+pub const HIGH_CONTRAST_BALL_SCALE: f32 = 1.25;
+// The gradient's hue drifts this many degrees either way over one cycle.
+pub const BACKGROUND_HUE_SWING: f32 = 20.0;
+pub const BACKGROUND_CYCLE: f32 = 30.0;
+pub const BACKGROUND_TOP_LIGHTEN: f32 = 0.15;
+pub const BACKGROUND_BOTTOM_DARKEN: f32 = 0.6;
+// The net starts to redden this many points before match point.
+pub const NET_TENSION_POINTS: u32 = 4;
+pub const NET_MATCH_POINT_COLOR: Color = Color::rgba(0.9, 0.1, 0.1, 0.8);
+// The default theme's background, and the fallback for a custom one.
+pub const CORNFLOWER_BLUE: Color = Color::rgb(0.392, 0.584, 0.929);
+pub const STAR_COUNT: usize = 160;
+pub const STAR_SIZE: f32 = 3.0;
+// Pixels per second for each starfield layer, from the farthest to the nearest.
+pub const STAR_LAYER_SPEEDS: [f32; 3] = [20.0, 60.0, 140.0];
+pub const PADDLE_MARGIN: f32 = 16.0;
+pub const GAMEPAD_DEADZONE: f32 = 0.2;
+pub const RUMBLE_DURATION_MS: u32 = 120;
+pub const SOUND_MIN_INTERVAL: f32 = 0.05;
+pub const POINT_FLASH_TIME: f32 = 0.5;
+pub const SERVE_COUNTDOWN: f32 = 3.0;
+pub const MATCH_INTRO_COUNTDOWN: f32 = 3.0;
+pub const MUSIC_VOLUME: f32 = 0.5;
+pub const FRAME_TIME_SAMPLES: usize = 30;
+pub const TRAIL_LENGTH: usize = 8;
+pub const PARTICLE_COUNT: usize = 8;
+pub const PARTICLE_LIFE: f32 = 0.4;
+pub const PARTICLE_SPEED: f32 = 300.0;
+pub const PARTICLE_SIZE: f32 = 4.0;
+pub const CELEBRATION_DURATION: f32 = 4.0;
+pub const CELEBRATION_TEXT_TIME: f32 = 0.6;
+// How much bigger the winner's paddle gets at its peak, and how fast it pulses.
+pub const CELEBRATION_PADDLE_GROWTH: f32 = 0.4;
+pub const CELEBRATION_PULSE_RATE: f32 = 3.0;
+// Confetti pieces per second, and how they fall.
+pub const CONFETTI_RATE: f32 = 60.0;
+pub const CONFETTI_SPEED: f32 = 500.0;
+pub const CONFETTI_DRIFT: f32 = 60.0;
+pub const CONFETTI_LIFE: f32 = 2.2;
+pub const HEATMAP_COLUMNS: usize = 32;
+pub const HEATMAP_ROWS: usize = 18;
+// The alpha of the hottest cell; the rest fade with their share of its count.
+pub const HEATMAP_MAX_ALPHA: f32 = 0.5;
+pub const SHAKE_DURATION: f32 = 0.3;
+pub const SHAKE_INTENSITY: f32 = 12.0;
+pub const HITSTOP_MIN_SPEED: f32 = 900.0;
+pub const HITSTOP_MIN_FRAMES: u32 = 2;
+pub const HITSTOP_MAX_FRAMES: u32 = 6;
+pub const NEAR_MISS_MARGIN: f32 = 24.0;
+pub const NEAR_MISS_TIME_SCALE: f32 = 0.3;
+pub const NEAR_MISS_RECOVERY: f32 = 0.6;
+pub const POWER_UP_INTERVAL: f32 = 8.0;
+pub const POWER_UP_SIZE: f32 = 40.0;
+pub const POWER_UP_DURATION: f32 = 6.0;
+pub const POWER_UP_GROW_SCALE: f32 = 1.5;
+pub const POWER_UP_SHRINK_SCALE: f32 = 0.6;
+// How long a reverse or freeze lasts on the paddle it hits.
+pub const EFFECT_DURATION: f32 = 2.5;
+pub const EFFECT_ICON_SIZE: f32 = 12.0;
+// A shield is a bar between the paddle and the goal line, this wide, set in
+// from the line by SHIELD_INSET and covering most of the court's height.
+pub const SHIELD_WIDTH: f32 = 6.0;
+pub const SHIELD_INSET: f32 = 2.0;
+pub const SHIELD_COVERAGE: f32 = 0.9;
+pub const MULTI_BALL_SPREAD: f32 = 15.0;
+pub const OBSTACLE_WIDTH: f32 = 40.0;
+pub const OBSTACLE_HEIGHT: f32 = 200.0;
+pub const OBSTACLE_SPEED: f32 = 200.0;
+pub const OBSTACLE_MARGIN: f32 = 40.0;
+pub const OBSTACLE_SPIN: f32 = 0.5;
+pub const PRACTICE_WALL_WIDTH: f32 = 24.0;
+pub const BRICK_COLUMNS: usize = 3;
+pub const BRICK_ROWS: usize = 8;
+pub const BRICK_WIDTH: f32 = 24.0;
+pub const BRICK_HEIGHT: f32 = 64.0;
+pub const BRICK_GAP: f32 = 12.0;
+
+pub const PLAYER1_TEXTURE_PATH: &str = "./img/player1.png";
+pub const PLAYER2_TEXTURE_PATH: &str = "./img/player2.png";
+pub const BALL_TEXTURE_PATH: &str = "./img/ball.png";
+// How often debug builds check those images for changes, in seconds.
+pub const TEXTURE_POLL_INTERVAL: f32 = 1.0;
+
+// Assets are baked into the binary so it runs on its own; a file at the usual
+// path still takes precedence so the art and sounds can be swapped out.
+pub const BALL_PNG: &[u8] = include_bytes!("../img/ball.png");
+pub const PLAYER1_PNG: &[u8] = include_bytes!("../img/player1.png");
+pub const PLAYER2_PNG: &[u8] = include_bytes!("../img/player2.png");
+pub const FONT_OTF: &[u8] = include_bytes!("../fonts/wheaton.otf");
+pub const PADDLE_LEFT_WAV: &[u8] = include_bytes!("../sfx/paddle_left.wav");
+pub const PADDLE_RIGHT_WAV: &[u8] = include_bytes!("../sfx/paddle_right.wav");
+pub const WALL_LEFT_WAV: &[u8] = include_bytes!("../sfx/wall_left.wav");
+pub const WALL_RIGHT_WAV: &[u8] = include_bytes!("../sfx/wall_right.wav");
+pub const SCORE_WAV: &[u8] = include_bytes!("../sfx/score.wav");
+pub const MUSIC_WAV: &[u8] = include_bytes!("../sfx/music.wav");
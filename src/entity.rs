@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use tetra::graphics::{Color, Rectangle};
+use tetra::math::Vec2;
+
+use crate::config::*;
+use crate::game::Side;
+
+// A steered paddle's next velocity along its track: towards `direction` times
+// its top speed while held, otherwise back towards rest, never past either.
+fn paddle_velocity(current: f32, direction: f32, max_speed: f32, dt: f32) -> f32 {
+    let target = direction.clamp(-1.0, 1.0) * max_speed;
+    let rate = if direction != 0.0 {
+        PADDLE_ACCELERATION
+    } else {
+        PADDLE_DECELERATION
+    };
+    let step = rate * dt;
+
+    let next = if (target - current).abs() <= step {
+        target
+    } else {
+        current + step * (target - current).signum()
+    };
+
+    next.clamp(-max_speed, max_speed)
+}
+
+// The geometry behind `Entity`'s size, bounds and centre. Only the height
+// scales: paddles grow and shrink along their track.
+fn scaled_size(base_size: Vec2<f32>, size_scale: f32, height_scale: f32) -> Vec2<f32> {
+    Vec2::new(base_size.x, base_size.y * size_scale * height_scale)
+}
+
+fn bounds_at(position: Vec2<f32>, size: Vec2<f32>) -> Rectangle {
+    Rectangle::new(position.x, position.y, size.x, size.y)
+}
+
+fn centre_of(position: Vec2<f32>, size: Vec2<f32>) -> Vec2<f32> {
+    position + size / 2.0
+}
+
+#[derive(Clone)]
+pub struct Entity {
+    // The size of the sprite it's drawn with, before any scaling. The texture
+    // itself stays with the drawing code, so a match can run without one.
+    pub base_size: Vec2<f32>,
+    pub position: Vec2<f32>,
+    // Where the entity was at the start of the current tick, so drawing can
+    // blend towards `position` between fixed updates.
+    previous_position: Vec2<f32>,
+    pub velocity: Vec2<f32>,
+    pub height_scale: f32,
+    pub rotation: f32,
+    // Balls only: spin from the last paddle hit, curving the ball's path.
+    pub spin: f32,
+    // Balls only: the paddle holding the ball with the catch assist, and how
+    // far below that paddle's top it's held. The velocity is kept for launch.
+    pub caught_by: Option<Side>,
+    pub catch_offset: f32,
+    // Paddles only: the handicap height multiplier, applied on top of any
+    // power-up's `height_scale`, and the top movement speed.
+    pub size_scale: f32,
+    pub speed: f32,
+    // Paddles only: time left in the current dash, and until the next.
+    dash_timer: f32,
+    pub dash_cooldown: f32,
+    // Paddles only: status effects from power-ups, dropped as they run out.
+    pub effects: Vec<Effect>,
+}
+
+impl Entity {
+    pub fn new(base_size: Vec2<f32>, position: Vec2<f32>) -> Entity {
+        Entity::with_velocity(base_size, position, Vec2::zero())
+    }
+
+    pub fn with_velocity(base_size: Vec2<f32>, position: Vec2<f32>, velocity: Vec2<f32>) -> Entity {
+        Entity {
+            base_size,
+            position,
+            previous_position: position,
+            velocity,
+            height_scale: 1.0,
+            rotation: 0.0,
+            spin: 0.0,
+            caught_by: None,
+            catch_offset: 0.0,
+            size_scale: 1.0,
+            speed: 0.0,
+            dash_timer: 0.0,
+            dash_cooldown: 0.0,
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn paddle(
+        base_size: Vec2<f32>,
+        position: Vec2<f32>,
+        speed: f32,
+        size_scale: f32,
+    ) -> Entity {
+        Entity {
+            size_scale,
+            speed,
+            ..Entity::new(base_size, position)
+        }
+    }
+
+    fn size(&self) -> Vec2<f32> {
+        scaled_size(self.base_size, self.size_scale, self.height_scale)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.size().x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.size().y
+    }
+
+    // Resizes about the centre so the paddle doesn't jump.
+    pub fn set_height_scale(&mut self, scale: f32) {
+        let centre_y = self.centre().y;
+        self.height_scale = scale;
+        self.position.y = centre_y - self.height() / 2.0;
+    }
+
+    pub fn bounds(&self) -> Rectangle {
+        bounds_at(self.position, self.size())
+    }
+
+    pub fn centre(&self) -> Vec2<f32> {
+        centre_of(self.position, self.size())
+    }
+
+    // Called at the start of every tick, and after any jump that shouldn't be
+    // drawn as a slide.
+    pub fn settle(&mut self) {
+        self.previous_position = self.position;
+    }
+
+    pub fn drawn_position(&self, blend: f32) -> Vec2<f32> {
+        Vec2::lerp(self.previous_position, self.position, blend)
+    }
+
+    pub fn drawn_bounds(&self, blend: f32) -> Rectangle {
+        bounds_at(self.drawn_position(blend), self.size())
+    }
+
+    pub fn has_effect(&self, kind: EffectKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    // A second hit of the same effect restarts its timer.
+    pub fn add_effect(&mut self, kind: EffectKind) {
+        self.effects.retain(|effect| effect.kind != kind);
+        self.effects.push(Effect {
+            kind,
+            time_left: EFFECT_DURATION,
+        });
+    }
+
+    pub fn update_effects(&mut self, dt: f32) {
+        for effect in &mut self.effects {
+            effect.time_left -= dt;
+        }
+
+        self.effects.retain(|effect| effect.time_left > 0.0);
+    }
+
+    fn top_speed(&self) -> f32 {
+        if self.dash_timer > 0.0 {
+            self.speed * DASH_MULTIPLIER
+        } else {
+            self.speed
+        }
+    }
+
+    // Moves a player-steered paddle along its track (x for the top and bottom
+    // paddles, y for the others). A dash needs a direction held and bursts
+    // straight to the dash speed.
+    pub fn steer(&mut self, direction: f32, dash: bool, horizontal: bool, dt: f32) {
+        // A freeze stops the paddle, not the clock on its dash.
+        self.dash_timer = (self.dash_timer - dt).max(0.0);
+        self.dash_cooldown = (self.dash_cooldown - dt).max(0.0);
+
+        if self.has_effect(EffectKind::Freeze) {
+            self.velocity = Vec2::zero();
+            return;
+        }
+
+        let direction = if self.has_effect(EffectKind::Reverse) {
+            -direction
+        } else {
+            direction
+        };
+
+        let dashed = dash && direction != 0.0 && self.dash_cooldown <= 0.0;
+
+        if dashed {
+            self.dash_timer = DASH_DURATION;
+            self.dash_cooldown = DASH_COOLDOWN;
+        }
+
+        let top_speed = self.top_speed();
+        let (along, position) = if horizontal {
+            (&mut self.velocity.x, &mut self.position.x)
+        } else {
+            (&mut self.velocity.y, &mut self.position.y)
+        };
+
+        *along = if dashed {
+            direction.clamp(-1.0, 1.0) * top_speed
+        } else {
+            paddle_velocity(*along, direction, top_speed, dt)
+        };
+        *position += *along * dt;
+    }
+
+    pub fn clamp_vertical(&mut self, min: f32, max: f32) {
+        self.position.y = self.position.y.clamp(min, max);
+    }
+
+    pub fn clamp_horizontal(&mut self, min: f32, max: f32) {
+        self.position.x = self.position.x.clamp(min, max);
+    }
+
+    pub fn state(&self) -> EntityState {
+        EntityState {
+            position: self.position,
+            velocity: self.velocity,
+            height_scale: self.height_scale,
+            rotation: self.rotation,
+            spin: self.spin,
+            caught_by: self.caught_by,
+            catch_offset: self.catch_offset,
+            dash_timer: self.dash_timer,
+            dash_cooldown: self.dash_cooldown,
+            effects: self.effects.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, state: &EntityState) {
+        self.position = state.position;
+        self.velocity = state.velocity;
+        self.height_scale = state.height_scale;
+        self.rotation = state.rotation;
+        self.spin = state.spin;
+        self.caught_by = state.caught_by;
+        self.catch_offset = state.catch_offset;
+        self.dash_timer = state.dash_timer;
+        self.dash_cooldown = state.dash_cooldown;
+        self.effects = state.effects.clone();
+        self.settle();
+    }
+}
+
+// Everything about an entity except its size, which comes from its texture.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct EntityState {
+    pub position: Vec2<f32>,
+    velocity: Vec2<f32>,
+    height_scale: f32,
+    rotation: f32,
+    #[serde(default)]
+    spin: f32,
+    #[serde(default)]
+    caught_by: Option<Side>,
+    #[serde(default)]
+    catch_offset: f32,
+    #[serde(default)]
+    dash_timer: f32,
+    #[serde(default)]
+    dash_cooldown: f32,
+    #[serde(default)]
+    effects: Vec<Effect>,
+}
+
+// Timed status effects on a paddle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum EffectKind {
+    // Up and down are swapped for the player steering it.
+    Reverse,
+    // It can't move at all.
+    Freeze,
+}
+
+impl EffectKind {
+    pub fn color(self) -> Color {
+        match self {
+            EffectKind::Reverse => Color::rgb(0.8, 0.3, 0.9),
+            EffectKind::Freeze => Color::rgb(0.6, 0.9, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub time_left: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_scales_only_the_height() {
+        let texture = Vec2::new(32.0, 160.0);
+
+        assert_eq!(scaled_size(texture, 1.0, 1.0), texture);
+        assert_eq!(scaled_size(texture, 1.5, 1.0), Vec2::new(32.0, 240.0));
+        assert_eq!(scaled_size(texture, 0.5, 1.5), Vec2::new(32.0, 120.0));
+    }
+
+    #[test]
+    fn bounds_start_at_the_position() {
+        let size = scaled_size(Vec2::new(32.0, 160.0), 1.0, 1.0);
+        let bounds = bounds_at(Vec2::new(16.0, 460.0), size);
+
+        assert_eq!(bounds, Rectangle::new(16.0, 460.0, 32.0, 160.0));
+        assert_eq!(bounds.right(), 48.0);
+        assert_eq!(bounds.bottom(), 620.0);
+    }
+
+    #[test]
+    fn centre_is_half_the_size_in() {
+        let ball = Vec2::new(24.0, 24.0);
+        assert_eq!(
+            centre_of(Vec2::new(948.0, 528.0), ball),
+            Vec2::new(960.0, 540.0)
+        );
+
+        // A grown paddle's centre moves down with its bottom edge.
+        let paddle = scaled_size(Vec2::new(32.0, 160.0), 1.0, 2.0);
+        assert_eq!(
+            centre_of(Vec2::new(16.0, 0.0), paddle),
+            Vec2::new(32.0, 160.0)
+        );
+    }
+
+    #[test]
+    fn paddles_ramp_up_and_coast_to_a_stop() {
+        let dt = 1.0 / 60.0;
+        let mut velocity = 0.0;
+
+        velocity = paddle_velocity(velocity, 1.0, PADDLE_SPEED, dt);
+        assert!(velocity > 0.0 && velocity < PADDLE_SPEED);
+
+        for _ in 0..60 {
+            velocity = paddle_velocity(velocity, 1.0, PADDLE_SPEED, dt);
+        }
+        assert_eq!(velocity, PADDLE_SPEED);
+
+        // Released, it keeps moving for a few frames before it stops.
+        velocity = paddle_velocity(velocity, 0.0, PADDLE_SPEED, dt);
+        assert!(velocity > 0.0);
+        for _ in 0..60 {
+            velocity = paddle_velocity(velocity, 0.0, PADDLE_SPEED, dt);
+        }
+        assert_eq!(velocity, 0.0);
+
+        // Faster than the paddle's top speed, say after a mouse drag, it's
+        // brought back within it.
+        assert_eq!(paddle_velocity(5000.0, 1.0, PADDLE_SPEED, dt), PADDLE_SPEED);
+    }
+
+    #[test]
+    fn states_saved_before_later_fields_still_load() {
+        let state = EntityState {
+            position: Vec2::new(16.0, 460.0),
+            height_scale: 1.5,
+            ..EntityState::default()
+        };
+        let mut json = serde_json::to_value(&state).unwrap();
+        for field in [
+            "spin",
+            "caught_by",
+            "catch_offset",
+            "dash_timer",
+            "dash_cooldown",
+            "effects",
+        ] {
+            json.as_object_mut().unwrap().remove(field);
+        }
+
+        let loaded: EntityState = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.position, state.position);
+        assert_eq!(loaded.height_scale, 1.5);
+        assert!(loaded.effects.is_empty());
+    }
+}
@@ -0,0 +1,7165 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tetra::audio::{Sound, SoundInstance};
+use tetra::graphics::mesh::{BufferUsage, IndexBuffer, Mesh, Vertex, VertexBuffer};
+use tetra::graphics::text::{Font, Text};
+use tetra::graphics::{self, Canvas, Color, DrawParams, Rectangle, Texture, TextureFormat};
+use tetra::input::{self, GamepadAxis, GamepadButton, Key};
+use tetra::math::{Mat4, Vec2};
+use tetra::time;
+use tetra::window::{self, get_height, get_width};
+use tetra::{Context, State};
+
+use crate::config::*;
+use crate::entity::{EffectKind, Entity, EntityState};
+
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window_width: i32,
+    pub window_height: i32,
+    pub fullscreen: bool,
+    ball_speed: f32,
+    paddle_speed: f32,
+    win_score: u32,
+    match_duration: f32,
+    obstacle_speed: f32,
+    theme: ThemeKind,
+    high_contrast: bool,
+    background: BackgroundMode,
+    // Replaces the theme's background colour, written "#RRGGBB". One that
+    // doesn't parse falls back to cornflower blue.
+    #[serde(
+        serialize_with = "serialize_hex_color",
+        deserialize_with = "deserialize_hex_color",
+        skip_serializing_if = "Option::is_none"
+    )]
+    background_color: Option<Color>,
+    // Off silences the sound effects and the music.
+    sound: bool,
+    // Pulls the ball down the court so it travels in arcs.
+    gravity: bool,
+    // An assist: holding the serve key as the ball arrives catches it on the
+    // paddle, and letting go launches it.
+    catch_assist: bool,
+    // Scales every sound and the music, from 0.0 to 1.0.
+    master_volume: f32,
+    // Handicaps: each player's speed falls back to `paddle_speed`, and the
+    // sizes scale the paddle's height.
+    player1_paddle_speed: Option<f32>,
+    player2_paddle_speed: Option<f32>,
+    player1_paddle_size: f32,
+    player2_paddle_size: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            window_width: WINDOW_WIDTH as i32,
+            window_height: WINDOW_HEIGHT as i32,
+            fullscreen: true,
+            ball_speed: BALL_SPEED,
+            paddle_speed: PADDLE_SPEED,
+            win_score: WIN_SCORE,
+            match_duration: MATCH_DURATION,
+            obstacle_speed: OBSTACLE_SPEED,
+            theme: ThemeKind::Cornflower,
+            high_contrast: false,
+            background: BackgroundMode::Flat,
+            background_color: None,
+            sound: true,
+            gravity: false,
+            catch_assist: false,
+            master_volume: 1.0,
+            player1_paddle_speed: None,
+            player2_paddle_speed: None,
+            player1_paddle_size: 1.0,
+            player2_paddle_size: 1.0,
+        }
+    }
+}
+
+impl Settings {
+    // A missing or unparseable file falls back to the defaults, but values that
+    // parse and are out of range are an error so typos don't go unnoticed.
+    pub fn load(path: &str) -> Result<Settings, String> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => {
+                eprintln!("Could not read {}, using defaults: {}", path, e);
+                return Ok(Settings::default());
+            }
+        };
+
+        let settings = match Settings::parse(&contents) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Could not parse {}, using defaults: {}", path, e);
+                return Ok(Settings::default());
+            }
+        };
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn parse(contents: &str) -> Result<Settings, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    fn save(&self, path: &str) {
+        let result = toml::to_string_pretty(self)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| std::fs::write(path, contents).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            eprintln!("Could not save settings to {}: {}", path, e);
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_width <= 0 || self.window_height <= 0 {
+            return Err(format!(
+                "window size must be positive, got {}x{}",
+                self.window_width, self.window_height
+            ));
+        }
+
+        if !is_positive(self.ball_speed) {
+            return Err(format!(
+                "ball_speed must be positive, got {}",
+                self.ball_speed
+            ));
+        }
+
+        if !is_positive(self.paddle_speed) {
+            return Err(format!(
+                "paddle_speed must be positive, got {}",
+                self.paddle_speed
+            ));
+        }
+
+        for (name, speed) in [
+            ("player1_paddle_speed", self.player1_paddle_speed),
+            ("player2_paddle_speed", self.player2_paddle_speed),
+        ] {
+            if let Some(speed) = speed.filter(|&speed| !is_positive(speed)) {
+                return Err(format!("{} must be positive, got {}", name, speed));
+            }
+        }
+
+        for (name, size) in [
+            ("player1_paddle_size", self.player1_paddle_size),
+            ("player2_paddle_size", self.player2_paddle_size),
+        ] {
+            if !is_positive(size) {
+                return Err(format!("{} must be positive, got {}", name, size));
+            }
+        }
+
+        if !is_positive(self.match_duration) {
+            return Err(format!(
+                "match_duration must be positive, got {}",
+                self.match_duration
+            ));
+        }
+
+        if !is_positive(self.obstacle_speed) {
+            return Err(format!(
+                "obstacle_speed must be positive, got {}",
+                self.obstacle_speed
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.master_volume) {
+            return Err(format!(
+                "master_volume must be between 0 and 1, got {}",
+                self.master_volume
+            ));
+        }
+
+        if self.win_score < MIN_WIN_SCORE {
+            return Err(format!("win_score must be at least {}", MIN_WIN_SCORE));
+        }
+
+        Ok(())
+    }
+}
+
+// Best-ever results, kept across runs.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Records {
+    longest_rally: u32,
+    fastest_ball: f32,
+    games_played: u32,
+}
+
+impl Records {
+    fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("pong-tetra-rust").join("records.json"))
+    }
+
+    // A missing or corrupt file just means starting from scratch.
+    fn load() -> Records {
+        let Some(path) = Records::path() else {
+            return Records::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Records::parse(&contents).unwrap_or_else(|e| {
+                eprintln!("Could not parse {}, starting fresh: {}", path.display(), e);
+                Records::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Records::default(),
+            Err(e) => {
+                eprintln!("Could not read {}, starting fresh: {}", path.display(), e);
+                Records::default()
+            }
+        }
+    }
+
+    fn parse(contents: &str) -> serde_json::Result<Records> {
+        serde_json::from_str(contents)
+    }
+
+    fn save(&self) {
+        let Some(path) = Records::path() else {
+            return;
+        };
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| {
+                let json = serde_json::to_string_pretty(self)?;
+                std::fs::write(&path, json)
+            });
+
+        if let Err(e) = result {
+            eprintln!("Could not save records to {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Counters for the current match, shown once it ends.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+struct MatchStats {
+    hits_p1: u32,
+    hits_p2: u32,
+    duration: f32,
+    max_ball_speed: f32,
+}
+
+fn game_over_message(headline: &str, stats: &MatchStats, longest_rally: u32) -> String {
+    format!(
+        "{}\n\nHits: {} - {}\nLongest rally: {}\nMatch time: {}\nTop speed: {:.0}\n\nPress Enter to Restart or Esc to quit game",
+        headline,
+        stats.hits_p1,
+        stats.hits_p2,
+        longest_rally,
+        timer_message(stats.duration),
+        stats.max_ball_speed
+    )
+}
+
+fn records_message(records: &Records) -> String {
+    format!(
+        "Longest rally: {}   Fastest ball: {:.0}   Games played: {}",
+        records.longest_rally, records.fastest_ball, records.games_played
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MenuOption {
+    OnePlayer,
+    TwoPlayer,
+    FourPlayer,
+    Practice,
+    Options,
+    Quit,
+}
+
+const MENU_OPTIONS: [MenuOption; 6] = [
+    MenuOption::OnePlayer,
+    MenuOption::TwoPlayer,
+    MenuOption::FourPlayer,
+    MenuOption::Practice,
+    MenuOption::Options,
+    MenuOption::Quit,
+];
+
+impl MenuOption {
+    fn label(self) -> &'static str {
+        match self {
+            MenuOption::OnePlayer => "1P vs CPU",
+            MenuOption::TwoPlayer => "2P",
+            MenuOption::FourPlayer => "4P Party",
+            MenuOption::Practice => "Practice",
+            MenuOption::Options => "Options",
+            MenuOption::Quit => "Quit",
+        }
+    }
+}
+
+// The adjustable entries on the options screen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum OptionItem {
+    WinScore,
+    BallSpeed,
+    Difficulty,
+    Sound,
+    Volume,
+    Gravity,
+    CatchAssist,
+    Back,
+}
+
+const OPTION_ITEMS: [OptionItem; 8] = [
+    OptionItem::WinScore,
+    OptionItem::BallSpeed,
+    OptionItem::Difficulty,
+    OptionItem::Sound,
+    OptionItem::Volume,
+    OptionItem::Gravity,
+    OptionItem::CatchAssist,
+    OptionItem::Back,
+];
+
+const OPTIONS_HINT: &str =
+    "Up/Down to select, Left/Right to change, Enter on Back to save and return";
+
+fn option_label(
+    item: OptionItem,
+    settings: &Settings,
+    difficulty: Difficulty,
+    master_volume: f32,
+) -> String {
+    match item {
+        OptionItem::WinScore => format!("Win score: {}", settings.win_score),
+        OptionItem::BallSpeed => format!("Ball speed: {:.0}", settings.ball_speed),
+        OptionItem::Difficulty => format!("AI difficulty: {}", difficulty.name()),
+        OptionItem::Sound => format!("Sound: {}", if settings.sound { "On" } else { "Off" }),
+        OptionItem::Volume => format!("Volume: {:.0}%", master_volume * 100.0),
+        OptionItem::Gravity => format!("Gravity: {}", if settings.gravity { "On" } else { "Off" }),
+        OptionItem::CatchAssist => format!(
+            "Catch assist: {}",
+            if settings.catch_assist { "On" } else { "Off" }
+        ),
+        OptionItem::Back => "Back".to_string(),
+    }
+}
+
+// Steps the volume in tenths, rounding so repeated steps land on them exactly.
+fn step_volume(volume: f32, step: i32) -> f32 {
+    (((volume + VOLUME_STEP * step as f32) / VOLUME_STEP).round() * VOLUME_STEP).clamp(0.0, 1.0)
+}
+
+// Steps a setting up (positive `step`) or down, staying within what
+// `Settings::validate` accepts. Difficulty and volume live on the game state
+// and are stepped there.
+fn adjust_setting(settings: &mut Settings, item: OptionItem, step: i32) {
+    match item {
+        OptionItem::WinScore => {
+            settings.win_score = (settings.win_score as i32 + step)
+                .clamp(MIN_WIN_SCORE as i32, MAX_WIN_SCORE as i32)
+                as u32;
+        }
+        OptionItem::BallSpeed => {
+            settings.ball_speed = (settings.ball_speed + BALL_SPEED_STEP * step as f32)
+                .clamp(MIN_OPTION_BALL_SPEED, MAX_BALL_SPEED);
+        }
+        OptionItem::Sound => settings.sound = !settings.sound,
+        OptionItem::Gravity => settings.gravity = !settings.gravity,
+        OptionItem::CatchAssist => settings.catch_assist = !settings.catch_assist,
+        OptionItem::Difficulty | OptionItem::Volume | OptionItem::Back => {}
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum GameStatus {
+    InPlay,
+    // Both players are a point from the winning score and level.
+    Deuce,
+    // This player wins the game with the next point.
+    MatchPoint(Side),
+    Won(Side),
+}
+
+impl GameStatus {
+    fn label(self) -> &'static str {
+        match self {
+            GameStatus::Deuce => "DEUCE",
+            GameStatus::MatchPoint(_) => "MATCH POINT",
+            GameStatus::InPlay | GameStatus::Won(_) => "",
+        }
+    }
+}
+
+// A game goes to the first player to reach `win_score` with a two-point lead.
+fn game_status(player1_score: u32, player2_score: u32, win_score: u32) -> GameStatus {
+    let threshold = win_score.saturating_sub(1);
+
+    for (side, score, other) in [
+        (Side::Left, player1_score, player2_score),
+        (Side::Right, player2_score, player1_score),
+    ] {
+        if score >= win_score && score >= other + 2 {
+            return GameStatus::Won(side);
+        }
+
+        if score >= threshold && score > other {
+            return GameStatus::MatchPoint(side);
+        }
+    }
+
+    if player1_score >= threshold && player1_score == player2_score {
+        GameStatus::Deuce
+    } else {
+        GameStatus::InPlay
+    }
+}
+
+fn rally_message(rally_hits: u32, longest_rally: u32) -> String {
+    format!("Rally: {} (best {})", rally_hits, longest_rally)
+}
+
+fn games_message(games_won: u32) -> String {
+    format!("Games: {}", games_won)
+}
+
+fn bricks_message(bricks: u32) -> String {
+    format!("Bricks: {}", bricks)
+}
+
+fn menu_message(
+    difficulty: Difficulty,
+    court: Court,
+    match_mode: MatchMode,
+    orientation: Orientation,
+) -> String {
+    format!(
+        "AI difficulty: {} (press D to change)\nCourt: {} (press O to change)\nMatch: {} (press G to change)\nLayout: {} (press V to change)\nUp/Down to select, Enter to choose, R to watch the last replay",
+        difficulty.name(),
+        court.name(),
+        match_mode.name(),
+        orientation.name()
+    )
+}
+
+fn timer_message(seconds: f32) -> String {
+    let seconds = seconds.max(0.0).ceil() as u32;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn load_texture(ctx: &mut Context, path: &str, embedded: &[u8]) -> tetra::Result<Texture> {
+    if Path::new(path).exists() {
+        match Texture::new(ctx, path) {
+            Ok(texture) => return Ok(texture),
+            Err(e) => eprintln!(
+                "Could not load texture {}, using the built-in one: {}",
+                path, e
+            ),
+        }
+    }
+
+    Texture::from_encoded(ctx, embedded)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum TextureSlot {
+    Player1,
+    Player2,
+    Ball,
+}
+
+// An image on disk that debug builds reload when it's saved, so art can be
+// tweaked while the game runs.
+struct WatchedTexture {
+    slot: TextureSlot,
+    path: &'static str,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedTexture {
+    fn new(slot: TextureSlot, path: &'static str) -> WatchedTexture {
+        WatchedTexture {
+            slot,
+            path,
+            modified: file_modified(path),
+        }
+    }
+}
+
+fn file_modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn watched_textures() -> Vec<WatchedTexture> {
+    if !cfg!(debug_assertions) {
+        return Vec::new();
+    }
+
+    vec![
+        WatchedTexture::new(TextureSlot::Player1, PLAYER1_TEXTURE_PATH),
+        WatchedTexture::new(TextureSlot::Player2, PLAYER2_TEXTURE_PATH),
+        WatchedTexture::new(TextureSlot::Ball, BALL_TEXTURE_PATH),
+    ]
+}
+
+fn solid_texture(ctx: &mut Context, size: Vec2<f32>, rgba: [u8; 4]) -> tetra::Result<Texture> {
+    let (width, height) = (size.x as i32, size.y as i32);
+    let data = rgba.repeat((width * height) as usize);
+
+    Texture::from_data(ctx, width, height, TextureFormat::Rgba8, &data)
+}
+
+fn texture_size(texture: &Texture) -> Vec2<f32> {
+    Vec2::new(texture.width() as f32, texture.height() as f32)
+}
+
+fn load_font(ctx: &mut Context, path: &str, size: f32) -> tetra::Result<Font> {
+    if Path::new(path).exists() {
+        match Font::vector(ctx, path, size) {
+            Ok(font) => return Ok(font),
+            Err(e) => eprintln!(
+                "Could not load font {}, using the built-in one: {}",
+                path, e
+            ),
+        }
+    }
+
+    Font::from_vector_file_data(ctx, FONT_OTF, size)
+}
+
+fn load_sound(path: &str, embedded: &[u8]) -> Sound {
+    if Path::new(path).exists() {
+        match Sound::new(path) {
+            Ok(sound) => return sound,
+            Err(e) => eprintln!(
+                "Could not load sound {}, using the built-in one: {}",
+                path, e
+            ),
+        }
+    }
+
+    Sound::from_encoded(embedded)
+}
+
+// Where the mouse sits along the axis the side paddles move on.
+fn mouse_along_paddles(ctx: &Context, orientation: Orientation) -> f32 {
+    match orientation {
+        Orientation::Horizontal => input::get_mouse_y(ctx).clamp(0.0, get_height(ctx) as f32),
+        Orientation::Vertical => input::get_mouse_x(ctx).clamp(0.0, get_width(ctx) as f32),
+    }
+}
+
+// Vertical input from a gamepad's left stick or d-pad, in [-1.0, 1.0] with
+// negative meaning up. Zero when the gamepad isn't connected.
+fn gamepad_vertical(ctx: &Context, gamepad_id: usize) -> f32 {
+    gamepad_direction(
+        ctx,
+        gamepad_id,
+        (GamepadButton::Up, GamepadButton::Down),
+        GamepadAxis::LeftStickY,
+    )
+}
+
+fn gamepad_horizontal(ctx: &Context, gamepad_id: usize) -> f32 {
+    gamepad_direction(
+        ctx,
+        gamepad_id,
+        (GamepadButton::Left, GamepadButton::Right),
+        GamepadAxis::LeftStickX,
+    )
+}
+
+// The d-pad wins over the stick, which has a deadzone.
+fn gamepad_direction(
+    ctx: &Context,
+    gamepad_id: usize,
+    (negative, positive): (GamepadButton, GamepadButton),
+    axis: GamepadAxis,
+) -> f32 {
+    if !input::is_gamepad_connected(ctx, gamepad_id) {
+        return 0.0;
+    }
+
+    if input::is_gamepad_button_down(ctx, gamepad_id, negative) {
+        return -1.0;
+    }
+
+    if input::is_gamepad_button_down(ctx, gamepad_id, positive) {
+        return 1.0;
+    }
+
+    let stick = input::get_gamepad_axis_position(ctx, gamepad_id, axis);
+
+    if stick.abs() < GAMEPAD_DEADZONE {
+        0.0
+    } else {
+        stick.clamp(-1.0, 1.0)
+    }
+}
+
+fn rumble(ctx: &mut Context, gamepad_id: usize, ball_speed: f32) {
+    if input::is_gamepad_connected(ctx, gamepad_id)
+        && input::is_gamepad_vibration_supported(ctx, gamepad_id)
+    {
+        let strength = (ball_speed / MAX_BALL_SPEED).clamp(0.2, 1.0);
+        input::start_gamepad_vibration(ctx, gamepad_id, strength, RUMBLE_DURATION_MS);
+    }
+}
+
+fn is_start_pressed(ctx: &Context) -> bool {
+    (0..2).any(|id| input::is_gamepad_button_pressed(ctx, id, GamepadButton::Start))
+}
+
+// Whether an action fires once on the update its key goes down, or on every
+// update for as long as it's held. Reading a toggle as held flips it on every
+// update the key stays down, so only these are continuous:
+// - paddle movement, from keys, d-pads and sticks
+// - serve keys, which charge the serve while held
+// Everything else is momentary: pause, mute, the theme, contrast and
+// background cycles, the AI toggles, court and menu choices, confirming,
+// fullscreen, save and load, shaking off a demo, screenshots, overlays and
+// answering the quit dialog.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Trigger {
+    Pressed,
+    Held,
+}
+
+fn is_key_triggered(ctx: &Context, key: Key, trigger: Trigger) -> bool {
+    match trigger {
+        Trigger::Pressed => input::is_key_pressed(ctx, key),
+        Trigger::Held => input::is_key_down(ctx, key),
+    }
+}
+
+// Enter or Start, once per tap, so a held key can't restart or choose twice.
+fn is_confirm_pressed(ctx: &Context) -> bool {
+    is_key_triggered(ctx, Key::Enter, Trigger::Pressed) || is_start_pressed(ctx)
+}
+
+fn is_any_input_pressed(ctx: &Context) -> bool {
+    input::get_keys_pressed(ctx).next().is_some()
+        || (0..2).any(|id| input::get_gamepad_buttons_pressed(ctx, id).next().is_some())
+}
+
+fn draw_centred(ctx: &mut Context, text: &mut Text, color: Color) {
+    let bounds = text.get_bounds(ctx).unwrap_or_default();
+    let position = Vec2::new(
+        (get_width(ctx) as f32 - bounds.width) / 2.0,
+        (get_height(ctx) as f32 - bounds.height) / 2.0,
+    );
+
+    text.draw(ctx, DrawParams::new().position(position).color(color));
+}
+
+fn draw_centred_x(ctx: &mut Context, text: &mut Text, y: f32, color: Color) {
+    let bounds = text.get_bounds(ctx).unwrap_or_default();
+    let position = Vec2::new((get_width(ctx) as f32 - bounds.width) / 2.0, y);
+
+    text.draw(ctx, DrawParams::new().position(position).color(color));
+}
+
+// Tetra can't pan a playing sound, so each panned effect ships as a left-only
+// and a right-only file which are mixed with a constant-power curve.
+struct PannedSound {
+    left: Sound,
+    right: Sound,
+}
+
+fn load_panned_sound(name: &str, left: &[u8], right: &[u8]) -> PannedSound {
+    PannedSound {
+        left: load_sound(&format!("./sfx/{}_left.wav", name), left),
+        right: load_sound(&format!("./sfx/{}_right.wav", name), right),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn edge(self) -> Edge {
+        match self {
+            Side::Left => Edge::Left,
+            Side::Right => Edge::Right,
+        }
+    }
+}
+
+// The court edge each player guards: players 1 and 2 on the left and right,
+// and in four-player mode players 3 and 4 on the top and bottom.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    fn player_name(self) -> &'static str {
+        match self {
+            Edge::Left => "Player 1",
+            Edge::Right => "Player 2",
+            Edge::Top => "Player 3",
+            Edge::Bottom => "Player 4",
+        }
+    }
+
+    fn gamepad_id(self) -> usize {
+        match self {
+            Edge::Left => 0,
+            Edge::Right => 1,
+            Edge::Top => 2,
+            Edge::Bottom => 3,
+        }
+    }
+
+    // Who scores when the ball leaves through this edge in a two-player game.
+    fn scorer(self) -> Option<Side> {
+        match self {
+            Edge::Left => Some(Side::Right),
+            Edge::Right => Some(Side::Left),
+            Edge::Top | Edge::Bottom => None,
+        }
+    }
+}
+
+// Only a ball travelling into the paddle's face counts as a hit; the returned
+// x places it just outside the paddle so an overlap can't flip it back again.
+fn paddle_contact(ball_width: f32, velocity_x: f32, paddle: Rectangle, side: Side) -> Option<f32> {
+    match side {
+        Side::Left if velocity_x < 0.0 => Some(paddle.x + paddle.width),
+        Side::Right if velocity_x > 0.0 => Some(paddle.x - ball_width),
+        _ => None,
+    }
+}
+
+// The top and bottom paddles' version of `paddle_contact`: returns the y that
+// places the ball just outside the paddle's face.
+fn horizontal_paddle_contact(
+    ball_height: f32,
+    velocity_y: f32,
+    paddle: Rectangle,
+    edge: Edge,
+) -> Option<f32> {
+    match edge {
+        Edge::Top if velocity_y < 0.0 => Some(paddle.y + paddle.height),
+        Edge::Bottom if velocity_y > 0.0 => Some(paddle.y - ball_height),
+        _ => None,
+    }
+}
+
+// Casts the ball's top-left corner from `from` to `to` against the paddle grown
+// by the ball's size, so a fast ball can't skip over a paddle between frames.
+// Returns the fraction of the move at which the ball first touches the paddle.
+fn sweep(from: Vec2<f32>, to: Vec2<f32>, ball_size: Vec2<f32>, paddle: Rectangle) -> Option<f32> {
+    let min = Vec2::new(paddle.x - ball_size.x, paddle.y - ball_size.y);
+    let max = Vec2::new(paddle.x + paddle.width, paddle.y + paddle.height);
+    let delta = to - from;
+
+    let mut t_min = 0.0_f32;
+    let mut t_max = 1.0_f32;
+
+    for (start, delta, min, max) in [
+        (from.x, delta.x, min.x, max.x),
+        (from.y, delta.y, min.y, max.y),
+    ] {
+        if delta == 0.0 {
+            if start <= min || start >= max {
+                return None;
+            }
+        } else {
+            let t1 = (min - start) / delta;
+            let t2 = (max - start) / delta;
+
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+
+            if t_min >= t_max {
+                return None;
+            }
+        }
+    }
+
+    Some(t_min)
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Brick {
+    bounds: Rectangle,
+    alive: bool,
+}
+
+// The grid of bricks as a whole, centred on the court.
+fn brick_grid(width: f32, height: f32) -> Rectangle {
+    let grid_width = BRICK_COLUMNS as f32 * (BRICK_WIDTH + BRICK_GAP) - BRICK_GAP;
+    let grid_height = BRICK_ROWS as f32 * (BRICK_HEIGHT + BRICK_GAP) - BRICK_GAP;
+
+    Rectangle::new(
+        (width - grid_width) / 2.0,
+        (height - grid_height) / 2.0,
+        grid_width,
+        grid_height,
+    )
+}
+
+fn brick_bounds(index: usize, width: f32, height: f32) -> Rectangle {
+    let grid = brick_grid(width, height);
+    let column = index % BRICK_COLUMNS;
+    let row = index / BRICK_COLUMNS;
+
+    Rectangle::new(
+        grid.x + column as f32 * (BRICK_WIDTH + BRICK_GAP),
+        grid.y + row as f32 * (BRICK_HEIGHT + BRICK_GAP),
+        BRICK_WIDTH,
+        BRICK_HEIGHT,
+    )
+}
+
+fn build_bricks(width: f32, height: f32) -> Vec<Brick> {
+    (0..BRICK_COLUMNS * BRICK_ROWS)
+        .map(|index| Brick {
+            bounds: brick_bounds(index, width, height),
+            alive: true,
+        })
+        .collect()
+}
+
+fn practice_wall(width: f32, height: f32) -> Rectangle {
+    Rectangle::new(
+        width - PADDLE_MARGIN - PRACTICE_WALL_WIDTH,
+        0.0,
+        PRACTICE_WALL_WIDTH,
+        height,
+    )
+}
+
+fn centred_obstacle(width: f32, height: f32) -> Rectangle {
+    Rectangle::new(
+        (width - OBSTACLE_WIDTH) / 2.0,
+        (height - OBSTACLE_HEIGHT) / 2.0,
+        OBSTACLE_WIDTH,
+        OBSTACLE_HEIGHT,
+    )
+}
+
+// Pushes an overlapping ball out along the axis it penetrates least and
+// reflects that component of its velocity, if it is moving into the obstacle.
+// The obstacle only moves vertically; glancing off its sides picks up spin
+// from that motion. Returns the corrected position and velocity.
+fn obstacle_bounce(
+    ball: Rectangle,
+    velocity: Vec2<f32>,
+    obstacle: Rectangle,
+    obstacle_velocity: f32,
+) -> Option<(Vec2<f32>, Vec2<f32>)> {
+    if !ball.intersects(&obstacle) {
+        return None;
+    }
+
+    let ball_centre = ball.center();
+    let obstacle_centre = obstacle.center();
+    let overlap_x = (ball.width + obstacle.width) / 2.0 - (ball_centre.x - obstacle_centre.x).abs();
+    let overlap_y =
+        (ball.height + obstacle.height) / 2.0 - (ball_centre.y - obstacle_centre.y).abs();
+
+    let mut position = Vec2::new(ball.x, ball.y);
+    let mut velocity = velocity;
+
+    if overlap_x < overlap_y {
+        let direction = (ball_centre.x - obstacle_centre.x).signum();
+
+        if velocity.x * direction >= 0.0 {
+            return None;
+        }
+
+        position.x += overlap_x * direction;
+        velocity.x = -velocity.x;
+        velocity.y += obstacle_velocity * OBSTACLE_SPIN;
+    } else {
+        let direction = (ball_centre.y - obstacle_centre.y).signum();
+        let relative_y = velocity.y - obstacle_velocity;
+
+        if relative_y * direction >= 0.0 {
+            return None;
+        }
+
+        position.y += overlap_y * direction;
+        velocity.y = obstacle_velocity - relative_y;
+    }
+
+    Some((position, velocity))
+}
+
+fn rotate(v: Vec2<f32>, angle: f32) -> Vec2<f32> {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+// Where the ball met the paddle, from 0 at its centre to 0.5 at either end
+// (positive above the centre), so taller paddles give gentler spin.
+fn hit_offset(ball: Rectangle, paddle: Rectangle) -> f32 {
+    centre_offset(ball.center(), paddle.center(), paddle.height)
+}
+
+fn centre_offset(ball_centre: Vec2<f32>, paddle_centre: Vec2<f32>, paddle_height: f32) -> f32 {
+    ((paddle_centre.y - ball_centre.y) / paddle_height).clamp(-MAX_HIT_OFFSET, MAX_HIT_OFFSET)
+}
+
+// The ball's velocity off a side paddle: returned by where it struck, no
+// steeper than MAX_BOUNCE_ANGLE and no faster than MAX_BALL_SPEED.
+fn resolve_paddle_hit(
+    ball_velocity: Vec2<f32>,
+    ball_centre: Vec2<f32>,
+    paddle_centre: Vec2<f32>,
+    paddle_height: f32,
+) -> Vec2<f32> {
+    let offset = centre_offset(ball_centre, paddle_centre, paddle_height);
+
+    clamp_speed(
+        limit_bounce_angle(paddle_return(ball_velocity, offset)),
+        MAX_BALL_SPEED,
+    )
+}
+
+// `paddle_return` for the top and bottom paddles, with the axes swapped.
+fn horizontal_paddle_return(velocity: Vec2<f32>, ball: Rectangle, paddle: Rectangle) -> Vec2<f32> {
+    let offset = (paddle.center().x - ball.center().x) / paddle.width;
+    let returned = paddle_return(Vec2::new(velocity.y, velocity.x), offset);
+
+    Vec2::new(returned.y, returned.x)
+}
+
+// Returns the ball off a paddle. `offset` is how far from the paddle's centre
+// the ball struck, as a fraction of the paddle height (positive above centre).
+// Centre hits come back faster and flatter; edge hits trade speed for spin.
+fn paddle_return(velocity: Vec2<f32>, offset: f32) -> Vec2<f32> {
+    let offset = offset.clamp(-MAX_HIT_OFFSET, MAX_HIT_OFFSET);
+    let edge = (offset.abs() * 2.0).min(1.0);
+    let sweet = (1.0 - edge) * (1.0 - edge);
+
+    let speed_factor = EDGE_SPEED_FACTOR + (SWEET_SPOT_SPEEDUP - EDGE_SPEED_FACTOR) * sweet;
+    let straighten = 1.0 - (1.0 - SWEET_SPOT_STRAIGHTEN) * sweet;
+
+    Vec2::new(
+        -(velocity.x + BALL_ACC * velocity.x.signum()) * speed_factor,
+        velocity.y * straighten - PADDLE_SPIN * offset * (1.0 + EDGE_SPIN_BONUS * edge),
+    )
+}
+
+// Turns a velocity steeper than MAX_BOUNCE_ANGLE back down to it, keeping its
+// speed and which way it's heading on each axis.
+fn limit_bounce_angle(velocity: Vec2<f32>) -> Vec2<f32> {
+    let max_angle = MAX_BOUNCE_ANGLE.to_radians();
+
+    if velocity.y.abs().atan2(velocity.x.abs()) <= max_angle {
+        return velocity;
+    }
+
+    let speed = velocity.magnitude();
+    Vec2::new(
+        speed * max_angle.cos() * velocity.x.signum(),
+        speed * max_angle.sin() * velocity.y.signum(),
+    )
+}
+
+// Extrapolates the ball's top-left corner until it reaches `target_x`, folding
+// the path back at the top and bottom walls. Returns None if the ball is moving
+// away from (or parallel to) the target.
+fn predict_intercept_y(
+    position: Vec2<f32>,
+    velocity: Vec2<f32>,
+    ball_height: f32,
+    target_x: f32,
+    court_height: f32,
+) -> Option<f32> {
+    let time = (target_x - position.x) / velocity.x;
+
+    if !time.is_finite() || time < 0.0 {
+        return None;
+    }
+
+    let range = (court_height - ball_height).max(0.0);
+    if range == 0.0 {
+        return Some(0.0);
+    }
+
+    let y = (position.y + velocity.y * time).rem_euclid(2.0 * range);
+    Some(if y > range { 2.0 * range - y } else { y })
+}
+
+// A full charge launches the serve `SERVE_MAX_POWER` times faster.
+fn serve_speed(base_speed: f32, charge: f32) -> f32 {
+    base_speed * (1.0 + (SERVE_MAX_POWER - 1.0) * charge.clamp(0.0, 1.0))
+}
+
+// The spin a paddle puts on the ball, in [-1.0, 1.0], positive curving it down
+// the screen. Edge hits and a paddle moving as it strikes brush the ball in
+// the same direction the paddle surface slides past it. `outgoing_x` is the
+// returned ball's horizontal speed, which curving down depends on.
+fn hit_spin(offset: f32, paddle_velocity_y: f32, outgoing_x: f32) -> f32 {
+    let curve_down =
+        paddle_velocity_y / PADDLE_SPEED * PADDLE_MOTION_SPIN - offset * HIT_OFFSET_SPIN;
+    (curve_down * outgoing_x.signum()).clamp(-1.0, 1.0)
+}
+
+// The Magnus effect: a sideways push proportional to spin, applied as a turn
+// so the ball keeps its speed, with the spin fading as it goes.
+fn apply_magnus(velocity: Vec2<f32>, spin: f32, dt: f32) -> (Vec2<f32>, f32) {
+    (
+        rotate(velocity, MAGNUS_STRENGTH * spin * dt),
+        spin * (-MAGNUS_DECAY * dt).exp(),
+    )
+}
+
+// Eases the vertical speed towards zero, leaving the horizontal speed alone.
+fn decay_spin(velocity: Vec2<f32>, dt: f32) -> Vec2<f32> {
+    Vec2::new(velocity.x, velocity.y * (-SPIN_DECAY * dt).exp())
+}
+
+fn clamp_speed(velocity: Vec2<f32>, max_speed: f32) -> Vec2<f32> {
+    let speed = velocity.magnitude();
+
+    if speed > max_speed {
+        velocity * (max_speed / speed)
+    } else {
+        velocity
+    }
+}
+
+// Serves at `speed` toward a random side, up to `SERVE_MAX_ANGLE` degrees off
+// the horizontal.
+fn random_serve_velocity<R: Rng>(rng: &mut R, speed: f32) -> Vec2<f32> {
+    let direction = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+    let angle = rng
+        .gen_range(-SERVE_MAX_ANGLE..=SERVE_MAX_ANGLE)
+        .to_radians();
+
+    Vec2::new(speed * angle.cos() * direction, speed * angle.sin())
+}
+
+// A near miss is a ball that cleared the paddle vertically, but only just.
+fn is_near_miss(ball: Rectangle, paddle: Rectangle, margin: f32) -> bool {
+    let gap = (paddle.y - (ball.y + ball.height)).max(ball.y - (paddle.y + paddle.height));
+    gap > 0.0 && gap <= margin
+}
+
+// Hits slower than `HITSTOP_MIN_SPEED` don't freeze at all; faster ones freeze
+// for longer the closer they get to `MAX_BALL_SPEED`.
+fn hitstop_frames(ball_speed: f32) -> u32 {
+    if ball_speed < HITSTOP_MIN_SPEED {
+        return 0;
+    }
+
+    let t = ((ball_speed - HITSTOP_MIN_SPEED) / (MAX_BALL_SPEED - HITSTOP_MIN_SPEED)).min(1.0);
+    HITSTOP_MIN_FRAMES + (t * (HITSTOP_MAX_FRAMES - HITSTOP_MIN_FRAMES) as f32).round() as u32
+}
+
+struct Particle {
+    position: Vec2<f32>,
+    velocity: Vec2<f32>,
+    life: f32,
+}
+
+fn spawn_particles<R: Rng>(particles: &mut Vec<Particle>, rng: &mut R, position: Vec2<f32>) {
+    for _ in 0..PARTICLE_COUNT {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = rng.gen_range(0.5..1.0) * PARTICLE_SPEED;
+
+        particles.push(Particle {
+            position,
+            velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+            life: PARTICLE_LIFE,
+        });
+    }
+}
+
+// Confetti for the winner's celebration, dropped in from above the court.
+fn spawn_confetti<R: Rng>(particles: &mut Vec<Particle>, rng: &mut R, width: f32) {
+    particles.push(Particle {
+        position: Vec2::new(rng.gen_range(0.0..width), -PARTICLE_SIZE),
+        velocity: Vec2::new(
+            rng.gen_range(-CONFETTI_DRIFT..CONFETTI_DRIFT),
+            rng.gen_range(0.5..1.0) * CONFETTI_SPEED,
+        ),
+        life: CONFETTI_LIFE,
+    });
+}
+
+// The winning paddle swells, pulsing, and shrinks back by the end.
+fn celebration_paddle_scale(time: f32) -> f32 {
+    if time >= CELEBRATION_DURATION {
+        return 1.0;
+    }
+
+    let swell = (time / CELEBRATION_DURATION * std::f32::consts::PI).sin();
+    let pulse = 1.0 + 0.3 * (time * CELEBRATION_PULSE_RATE * std::f32::consts::TAU).sin();
+    1.0 + CELEBRATION_PADDLE_GROWTH * swell * pulse
+}
+
+// The headline grows in from nothing and eases to its full size.
+fn celebration_text_scale(time: f32) -> f32 {
+    let t = (time / CELEBRATION_TEXT_TIME).clamp(0.0, 1.0);
+    1.0 - (1.0 - t) * (1.0 - t)
+}
+
+// Which of player1-player4 won, if the match had a winner.
+fn winner_index(winner: &str) -> Option<usize> {
+    [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom]
+        .iter()
+        .position(|edge| edge.player_name() == winner)
+}
+
+fn update_particles(particles: &mut Vec<Particle>, dt: f32) {
+    for particle in particles.iter_mut() {
+        particle.position += particle.velocity * dt;
+        particle.life -= dt;
+    }
+
+    particles.retain(|particle| particle.life > 0.0);
+}
+
+// Where the ball has been this match, counted per update in a coarse grid
+// laid over the court.
+struct Heatmap {
+    counts: [[u32; HEATMAP_COLUMNS]; HEATMAP_ROWS],
+    // The busiest cell's count.
+    hottest: u32,
+}
+
+impl Heatmap {
+    fn new() -> Heatmap {
+        Heatmap {
+            counts: [[0; HEATMAP_COLUMNS]; HEATMAP_ROWS],
+            hottest: 0,
+        }
+    }
+
+    // The column and row under a point, or none for a ball off the court.
+    fn cell(position: Vec2<f32>, court: Vec2<f32>) -> Option<(usize, usize)> {
+        if position.x < 0.0 || position.y < 0.0 || position.x >= court.x || position.y >= court.y {
+            return None;
+        }
+
+        let column = (position.x / court.x * HEATMAP_COLUMNS as f32) as usize;
+        let row = (position.y / court.y * HEATMAP_ROWS as f32) as usize;
+        Some((column.min(HEATMAP_COLUMNS - 1), row.min(HEATMAP_ROWS - 1)))
+    }
+
+    fn record(&mut self, position: Vec2<f32>, court: Vec2<f32>) {
+        if let Some((column, row)) = Heatmap::cell(position, court) {
+            self.counts[row][column] += 1;
+            self.hottest = self.hottest.max(self.counts[row][column]);
+        }
+    }
+
+    // Each cell's share of the busiest cell's count, from 0 to 1.
+    fn heat(&self, column: usize, row: usize) -> f32 {
+        if self.hottest == 0 {
+            0.0
+        } else {
+            self.counts[row][column] as f32 / self.hottest as f32
+        }
+    }
+}
+
+struct Star {
+    position: Vec2<f32>,
+    layer_speed: f32,
+    brightness: f32,
+}
+
+// Nearer layers are brighter, so the faster stars read as closer.
+fn build_stars<R: Rng>(rng: &mut R, width: f32, height: f32) -> Vec<Star> {
+    (0..STAR_COUNT)
+        .map(|_| {
+            let layer = rng.gen_range(0..STAR_LAYER_SPEEDS.len());
+            let depth = (layer + 1) as f32 / STAR_LAYER_SPEEDS.len() as f32;
+
+            Star {
+                position: Vec2::new(rng.gen_range(0.0..width), rng.gen_range(0.0..height)),
+                layer_speed: STAR_LAYER_SPEEDS[layer],
+                brightness: depth * rng.gen_range(0.6..1.0),
+            }
+        })
+        .collect()
+}
+
+// Only the oldest toast counts down; the rest wait their turn so none is missed.
+fn advance_toasts(toasts: &mut VecDeque<(String, f32)>, dt: f32) {
+    if let Some((_, remaining)) = toasts.front_mut() {
+        *remaining -= dt;
+        if *remaining <= 0.0 {
+            toasts.pop_front();
+        }
+    }
+}
+
+// Stars drift left and wrap around, including after the window is resized.
+fn scroll_stars(stars: &mut [Star], dt: f32, width: f32, height: f32) {
+    for star in stars {
+        star.position.x = (star.position.x - star.layer_speed * dt).rem_euclid(width);
+        star.position.y = star.position.y.rem_euclid(height);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum PowerUpKind {
+    Grow,
+    Shrink,
+    MultiBall,
+    // These two hit the collector's opponent.
+    Reverse,
+    Freeze,
+    Shield,
+}
+
+impl PowerUpKind {
+    fn height_scale(self) -> f32 {
+        match self {
+            PowerUpKind::Grow => POWER_UP_GROW_SCALE,
+            PowerUpKind::Shrink => POWER_UP_SHRINK_SCALE,
+            PowerUpKind::MultiBall
+            | PowerUpKind::Reverse
+            | PowerUpKind::Freeze
+            | PowerUpKind::Shield => 1.0,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            PowerUpKind::Grow => Color::rgb(0.2, 0.9, 0.3),
+            PowerUpKind::Shrink => Color::rgb(0.9, 0.2, 0.2),
+            PowerUpKind::MultiBall => Color::rgb(0.3, 0.5, 1.0),
+            PowerUpKind::Reverse => EffectKind::Reverse.color(),
+            PowerUpKind::Freeze => EffectKind::Freeze.color(),
+            PowerUpKind::Shield => Color::rgb(1.0, 0.85, 0.2),
+        }
+    }
+}
+
+// The shield guarding a side's goal line in a court of this size.
+fn shield_bounds(side: Side, court: Vec2<f32>) -> Rectangle {
+    let height = court.y * SHIELD_COVERAGE;
+    let x = match side {
+        Side::Left => SHIELD_INSET,
+        Side::Right => court.x - SHIELD_INSET - SHIELD_WIDTH,
+    };
+
+    Rectangle::new(x, (court.y - height) / 2.0, SHIELD_WIDTH, height)
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct PowerUp {
+    kind: PowerUpKind,
+    position: Vec2<f32>,
+}
+
+impl PowerUp {
+    fn bounds(&self) -> Rectangle {
+        Rectangle::new(
+            self.position.x,
+            self.position.y,
+            POWER_UP_SIZE,
+            POWER_UP_SIZE,
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Scene {
+    Menu,
+    Options,
+    Playing,
+    GameOver,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+enum ControlMode {
+    #[default]
+    Human,
+    Ai,
+    // Practice: a full-height wall stands in for player 2.
+    Wall,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct PaddleKeys {
+    up: Key,
+    down: Key,
+    serve: Key,
+    dash: Key,
+}
+
+impl PaddleKeys {
+    // -1.0 for up, 1.0 for down, 0.0 when neither or both are held. The top
+    // and bottom paddles use up for left and down for right.
+    fn direction<F: Fn(Key) -> bool>(self, is_down: F) -> f32 {
+        let mut direction = 0.0;
+
+        if is_down(self.up) {
+            direction -= 1.0;
+        }
+
+        if is_down(self.down) {
+            direction += 1.0;
+        }
+
+        direction
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Controls {
+    player1: PaddleKeys,
+    player2: PaddleKeys,
+    player3: PaddleKeys,
+    player4: PaddleKeys,
+}
+
+impl Default for Controls {
+    fn default() -> Controls {
+        Controls {
+            player1: PaddleKeys {
+                up: Key::W,
+                down: Key::S,
+                serve: Key::Space,
+                dash: Key::LeftShift,
+            },
+            player2: PaddleKeys {
+                up: Key::Up,
+                down: Key::Down,
+                serve: Key::RightShift,
+                dash: Key::RightCtrl,
+            },
+            player3: PaddleKeys {
+                up: Key::J,
+                down: Key::L,
+                serve: Key::K,
+                dash: Key::U,
+            },
+            player4: PaddleKeys {
+                up: Key::NumPad4,
+                down: Key::NumPad6,
+                serve: Key::NumPad5,
+                dash: Key::NumPad0,
+            },
+        }
+    }
+}
+
+impl Controls {
+    // In the vertical layout "up" on each paddle is towards court y = 0:
+    // screen left for players 1 and 2, and screen down for players 3 and 4.
+    fn for_orientation(orientation: Orientation) -> Controls {
+        match orientation {
+            Orientation::Horizontal => Controls::default(),
+            Orientation::Vertical => Controls {
+                player1: PaddleKeys {
+                    up: Key::A,
+                    down: Key::D,
+                    serve: Key::Space,
+                    dash: Key::LeftShift,
+                },
+                player2: PaddleKeys {
+                    up: Key::Left,
+                    down: Key::Right,
+                    serve: Key::RightShift,
+                    dash: Key::RightCtrl,
+                },
+                player3: PaddleKeys {
+                    up: Key::K,
+                    down: Key::I,
+                    serve: Key::J,
+                    dash: Key::U,
+                },
+                player4: PaddleKeys {
+                    up: Key::NumPad2,
+                    down: Key::NumPad8,
+                    serve: Key::NumPad5,
+                    dash: Key::NumPad0,
+                },
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Theme {
+    background: Color,
+    net: Color,
+    text: Color,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ThemeKind {
+    Classic,
+    Cornflower,
+    Neon,
+}
+
+impl Theme {
+    fn high_contrast() -> Theme {
+        Theme {
+            background: Color::BLACK,
+            net: Color::WHITE,
+            text: Color::WHITE,
+        }
+    }
+}
+
+impl ThemeKind {
+    fn name(self) -> &'static str {
+        match self {
+            ThemeKind::Classic => "Classic",
+            ThemeKind::Cornflower => "Cornflower",
+            ThemeKind::Neon => "Neon",
+        }
+    }
+
+    fn next(self) -> ThemeKind {
+        match self {
+            ThemeKind::Classic => ThemeKind::Cornflower,
+            ThemeKind::Cornflower => ThemeKind::Neon,
+            ThemeKind::Neon => ThemeKind::Classic,
+        }
+    }
+
+    fn theme(self) -> Theme {
+        match self {
+            ThemeKind::Classic => Theme {
+                background: Color::BLACK,
+                net: Color::rgba(1.0, 1.0, 1.0, 0.5),
+                text: Color::WHITE,
+            },
+            ThemeKind::Cornflower => Theme {
+                background: CORNFLOWER_BLUE,
+                net: Color::rgba(1.0, 1.0, 1.0, 0.3),
+                text: Color::WHITE,
+            },
+            ThemeKind::Neon => Theme {
+                background: Color::rgb(0.05, 0.0, 0.1),
+                net: Color::rgba(1.0, 0.0, 0.8, 0.5),
+                text: Color::rgb(0.0, 1.0, 0.9),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BackgroundMode {
+    // The theme's background colour.
+    Flat,
+    // A top-to-bottom gradient of the theme's colour whose hue slowly drifts.
+    Gradient,
+    // Layers of stars scrolling at different speeds over the theme's colour.
+    Starfield,
+}
+
+impl BackgroundMode {
+    fn next(self) -> BackgroundMode {
+        match self {
+            BackgroundMode::Flat => BackgroundMode::Gradient,
+            BackgroundMode::Gradient => BackgroundMode::Starfield,
+            BackgroundMode::Starfield => BackgroundMode::Flat,
+        }
+    }
+}
+
+// Rotates the colour about the grey axis, which keeps greys unchanged and
+// brightness roughly the same.
+fn shift_hue(color: Color, degrees: f32) -> Color {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let third = (1.0 - cos) / 3.0;
+    let root = (1.0f32 / 3.0).sqrt() * sin;
+    let (a, b, c) = (cos + third, third - root, third + root);
+
+    Color::rgba(
+        color.r * a + color.g * b + color.b * c,
+        color.r * c + color.g * a + color.b * b,
+        color.r * b + color.g * c + color.b * a,
+        color.a,
+    )
+    .clamp()
+}
+
+// The top and bottom colours of the gradient background at `time` seconds.
+fn background_gradient(base: Color, time: f32) -> (Color, Color) {
+    let phase = time / BACKGROUND_CYCLE * std::f32::consts::TAU;
+    let shifted = shift_hue(base, phase.sin() * BACKGROUND_HUE_SWING);
+    let top = Color::rgba(
+        shifted.r + (1.0 - shifted.r) * BACKGROUND_TOP_LIGHTEN,
+        shifted.g + (1.0 - shifted.g) * BACKGROUND_TOP_LIGHTEN,
+        shifted.b + (1.0 - shifted.b) * BACKGROUND_TOP_LIGHTEN,
+        shifted.a,
+    );
+    let bottom = Color::rgba(
+        shifted.r * BACKGROUND_BOTTOM_DARKEN,
+        shifted.g * BACKGROUND_BOTTOM_DARKEN,
+        shifted.b * BACKGROUND_BOTTOM_DARKEN,
+        shifted.a,
+    );
+
+    (top, bottom)
+}
+
+// "#RRGGBB", or the same without the "#", as a colour with 0-1 channels.
+fn parse_hex_color(s: &str) -> Result<Color, String> {
+    let digits = s.strip_prefix('#').unwrap_or(s);
+
+    if digits.len() != 6 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("expected a colour like #RRGGBB, got {:?}", s));
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap() as f32 / 255.0;
+    Ok(Color::rgb(channel(0), channel(2), channel(4)))
+}
+
+fn format_hex_color(color: Color) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02X}{:02X}{:02X}",
+        channel(color.r),
+        channel(color.g),
+        channel(color.b)
+    )
+}
+
+fn serialize_hex_color<S: serde::Serializer>(
+    color: &Option<Color>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match color {
+        Some(color) => serializer.serialize_str(&format_hex_color(*color)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_hex_color<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    let hex = Option::<String>::deserialize(deserializer)?;
+
+    Ok(hex.map(|hex| {
+        parse_hex_color(&hex).unwrap_or_else(|e| {
+            eprintln!("Invalid background_color, using cornflower blue: {}", e);
+            CORNFLOWER_BLUE
+        })
+    }))
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::rgba(
+        from.r + (to.r - from.r) * t,
+        from.g + (to.g - from.g) * t,
+        from.b + (to.b - from.b) * t,
+        from.a + (to.a - from.a) * t,
+    )
+}
+
+// 0 for most of a game, rising to 1 once the leader is a point from winning.
+fn net_tension(player1_score: u32, player2_score: u32, win_score: u32) -> f32 {
+    let remaining = win_score.saturating_sub(player1_score.max(player2_score));
+    let fade = remaining.saturating_sub(1) as f32 / NET_TENSION_POINTS as f32;
+
+    (1.0 - fade).clamp(0.0, 1.0)
+}
+
+// High-contrast mode overrides whichever theme is selected.
+fn active_theme(settings: &Settings) -> Theme {
+    if settings.high_contrast {
+        return Theme::high_contrast();
+    }
+
+    let mut theme = settings.theme.theme();
+    if let Some(background) = settings.background_color {
+        theme.background = background;
+    }
+    theme
+}
+
+// The game always runs with the paddles on the left and right of its own
+// court coordinates. The vertical layout draws that court turned a quarter
+// turn, so player 1 sits at the bottom and player 2 at the top, and reads
+// input along the turned axis.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+enum Orientation {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+impl Orientation {
+    fn name(self) -> &'static str {
+        match self {
+            Orientation::Horizontal => "Horizontal",
+            Orientation::Vertical => "Vertical",
+        }
+    }
+
+    fn next(self) -> Orientation {
+        match self {
+            Orientation::Horizontal => Orientation::Vertical,
+            Orientation::Vertical => Orientation::Horizontal,
+        }
+    }
+}
+
+// The court's size in its own coordinates.
+fn court_size(screen: Vec2<f32>, orientation: Orientation) -> Vec2<f32> {
+    match orientation {
+        Orientation::Horizontal => screen,
+        Orientation::Vertical => Vec2::new(screen.y, screen.x),
+    }
+}
+
+// Maps court coordinates to the screen: court x runs up the screen from the
+// bottom edge, and court y runs left to right.
+fn court_transform(screen: Vec2<f32>, orientation: Orientation) -> Mat4<f32> {
+    match orientation {
+        Orientation::Horizontal => Mat4::identity(),
+        Orientation::Vertical => {
+            Mat4::<f32>::translation_2d(Vec2::new(0.0, screen.y))
+                * Mat4::rotation_z(-std::f32::consts::FRAC_PI_2)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum MatchMode {
+    // First to the winning score, over a series of games.
+    Points,
+    // Most points when the clock runs out.
+    Timed,
+}
+
+impl MatchMode {
+    fn name(self) -> &'static str {
+        match self {
+            MatchMode::Points => "Points",
+            MatchMode::Timed => "Timed",
+        }
+    }
+
+    fn next(self) -> MatchMode {
+        match self {
+            MatchMode::Points => MatchMode::Timed,
+            MatchMode::Timed => MatchMode::Points,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Court {
+    Open,
+    Obstacle,
+    MovingObstacle,
+    Bricks,
+}
+
+impl Court {
+    fn name(self) -> &'static str {
+        match self {
+            Court::Open => "Open",
+            Court::Obstacle => "Obstacle",
+            Court::MovingObstacle => "Moving obstacle",
+            Court::Bricks => "Bricks",
+        }
+    }
+
+    fn next(self) -> Court {
+        match self {
+            Court::Open => Court::Obstacle,
+            Court::Obstacle => Court::MovingObstacle,
+            Court::MovingObstacle => Court::Bricks,
+            Court::Bricks => Court::Open,
+        }
+    }
+
+    fn has_obstacle(self) -> bool {
+        matches!(self, Court::Obstacle | Court::MovingObstacle)
+    }
+}
+
+// Steers the AI paddle towards its target with a PID controller, so it eases
+// in and settles instead of snapping.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+struct AiController {
+    prev_error: f32,
+    integral: f32,
+}
+
+impl AiController {
+    // Returns the paddle velocity for this step, capped at `max_speed`.
+    fn step(&mut self, error: f32, dt: f32, max_speed: f32) -> f32 {
+        let integral = (self.integral + error * dt).clamp(-AI_INTEGRAL_LIMIT, AI_INTEGRAL_LIMIT);
+        let derivative = (error - self.prev_error) / dt;
+        self.prev_error = error;
+
+        let output = AI_KP * error + AI_KI * integral + AI_KD * derivative;
+
+        // Stop integrating while saturated so long chases don't wind up a
+        // big overshoot.
+        if output.abs() < max_speed {
+            self.integral = integral;
+        }
+
+        output.clamp(-max_speed, max_speed)
+    }
+
+    fn reset(&mut self) {
+        *self = AiController::default();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            Difficulty::Medium => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    fn previous(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Hard,
+            Difficulty::Medium => Difficulty::Easy,
+            Difficulty::Hard => Difficulty::Medium,
+        }
+    }
+
+    /// Fraction of the paddle speed the AI paddle may move at. Hard stays
+    /// below 1.0 so a well-angled shot can still outrun it.
+    fn speed_factor(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Medium => 0.75,
+            Difficulty::Hard => 0.9,
+        }
+    }
+
+    /// How far, in pixels, the AI aims behind the ball's vertical travel.
+    fn aim_error(self) -> f32 {
+        match self {
+            Difficulty::Easy => 48.0,
+            Difficulty::Medium => 24.0,
+            Difficulty::Hard => 4.0,
+        }
+    }
+}
+
+// Everything a playing frame reads from the players, so a match can be
+// recorded and fed back in exactly.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+struct FrameInput {
+    // Keyboard and gamepad movement, positive for down.
+    player1: f32,
+    player2: f32,
+    // The top and bottom paddles, positive for right.
+    player3: f32,
+    player4: f32,
+    // Only set on frames where the mouse moved.
+    mouse_y: Option<f32>,
+    serve1: bool,
+    serve2: bool,
+    // Dash presses for players 1 to 4.
+    dash: [bool; 4],
+    pause: bool,
+    toggle_ai: bool,
+    toggle_extra_ai: bool,
+    next_court: bool,
+}
+
+impl FrameInput {
+    // Movement is in court coordinates: in the vertical layout the side
+    // paddles' sticks are read across the screen and the others' up it.
+    fn read(
+        ctx: &Context,
+        controls: &Controls,
+        orientation: Orientation,
+        last_mouse_y: f32,
+    ) -> FrameInput {
+        let is_down = |key| is_key_triggered(ctx, key, Trigger::Held);
+        let is_pressed = |key| is_key_triggered(ctx, key, Trigger::Pressed);
+        let dash_pressed = |key, id| {
+            is_pressed(key) || input::is_gamepad_button_pressed(ctx, id, GamepadButton::B)
+        };
+        let mouse_y = mouse_along_paddles(ctx, orientation);
+        let (side_stick, end_stick) = match orientation {
+            Orientation::Horizontal => (
+                gamepad_vertical as fn(&Context, usize) -> f32,
+                gamepad_horizontal as fn(&Context, usize) -> f32,
+            ),
+            Orientation::Vertical => (
+                gamepad_horizontal as fn(&Context, usize) -> f32,
+                (|ctx, id| -gamepad_vertical(ctx, id)) as fn(&Context, usize) -> f32,
+            ),
+        };
+
+        FrameInput {
+            player1: controls.player1.direction(is_down) + side_stick(ctx, 0),
+            player2: controls.player2.direction(is_down) + side_stick(ctx, 1),
+            player3: controls.player3.direction(is_down) + end_stick(ctx, 2),
+            player4: controls.player4.direction(is_down) + end_stick(ctx, 3),
+            mouse_y: (mouse_y != last_mouse_y).then_some(mouse_y),
+            serve1: is_down(controls.player1.serve)
+                || input::is_gamepad_button_down(ctx, 0, GamepadButton::A),
+            serve2: is_down(controls.player2.serve)
+                || input::is_gamepad_button_down(ctx, 1, GamepadButton::A),
+            dash: [
+                dash_pressed(controls.player1.dash, 0),
+                dash_pressed(controls.player2.dash, 1),
+                dash_pressed(controls.player3.dash, 2),
+                dash_pressed(controls.player4.dash, 3),
+            ],
+            pause: is_pressed(Key::P),
+            toggle_ai: is_pressed(Key::F1),
+            toggle_extra_ai: is_pressed(Key::F2),
+            next_court: is_pressed(Key::O),
+        }
+    }
+
+    fn serve(&self, side: Side) -> bool {
+        match side {
+            Side::Left => self.serve1,
+            Side::Right => self.serve2,
+        }
+    }
+}
+
+// A recorded match: the seed it started from, the modes it was played in and
+// the input for every playing frame. Updates run on a fixed timestep, so
+// feeding the frames back in reproduces the match. Modes newer than the replay
+// format fall back to their defaults, as the match was played without them.
+#[derive(Clone, Serialize, Deserialize)]
+struct Replay {
+    seed: u64,
+    #[serde(default)]
+    four_player: bool,
+    player2_control: ControlMode,
+    #[serde(default)]
+    extra_control: ControlMode,
+    difficulty: Difficulty,
+    court: Court,
+    match_mode: MatchMode,
+    #[serde(default)]
+    orientation: Orientation,
+    #[serde(default)]
+    gravity: bool,
+    #[serde(default)]
+    catch_assist: bool,
+    #[serde(default)]
+    settings: ReplaySettings,
+    frames: Vec<FrameInput>,
+}
+
+// The settings from the config or the options screen that change how a match
+// plays out, so a replay isn't played back by whatever they are now.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct ReplaySettings {
+    ball_speed: f32,
+    paddle_speed: f32,
+    win_score: u32,
+    match_duration: f32,
+    obstacle_speed: f32,
+    player1_paddle_speed: Option<f32>,
+    player2_paddle_speed: Option<f32>,
+    player1_paddle_size: f32,
+    player2_paddle_size: f32,
+}
+
+impl Default for ReplaySettings {
+    fn default() -> ReplaySettings {
+        ReplaySettings::of(&Settings::default())
+    }
+}
+
+impl ReplaySettings {
+    fn of(settings: &Settings) -> ReplaySettings {
+        ReplaySettings {
+            ball_speed: settings.ball_speed,
+            paddle_speed: settings.paddle_speed,
+            win_score: settings.win_score,
+            match_duration: settings.match_duration,
+            obstacle_speed: settings.obstacle_speed,
+            player1_paddle_speed: settings.player1_paddle_speed,
+            player2_paddle_speed: settings.player2_paddle_speed,
+            player1_paddle_size: settings.player1_paddle_size,
+            player2_paddle_size: settings.player2_paddle_size,
+        }
+    }
+
+    fn apply(&self, settings: Settings) -> Settings {
+        Settings {
+            ball_speed: self.ball_speed,
+            paddle_speed: self.paddle_speed,
+            win_score: self.win_score,
+            match_duration: self.match_duration,
+            obstacle_speed: self.obstacle_speed,
+            player1_paddle_speed: self.player1_paddle_speed,
+            player2_paddle_speed: self.player2_paddle_speed,
+            player1_paddle_size: self.player1_paddle_size,
+            player2_paddle_size: self.player2_paddle_size,
+            ..settings
+        }
+    }
+}
+
+impl Replay {
+    fn load(path: &str) -> Result<Replay, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
+
+    fn save(&self, path: &str) {
+        let result = serde_json::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            eprintln!("Could not save replay to {}: {}", path, e);
+        }
+    }
+}
+
+struct Playback {
+    replay: Replay,
+    frame: usize,
+    // The player's own settings, put back when the replay ends.
+    live_settings: Settings,
+}
+
+impl Playback {
+    fn new(replay: Replay, live_settings: Settings) -> Playback {
+        Playback {
+            replay,
+            frame: 0,
+            live_settings,
+        }
+    }
+
+    // The live settings with the replay's modes in place of the player's.
+    fn settings(&self) -> Settings {
+        Settings {
+            gravity: self.replay.gravity,
+            catch_assist: self.replay.catch_assist,
+            ..self.replay.settings.apply(self.live_settings)
+        }
+    }
+
+    fn next_input(&mut self) -> Option<FrameInput> {
+        let input = self.replay.frames.get(self.frame).copied();
+        self.frame += 1;
+        input
+    }
+}
+
+// Writes the CSV about once a second, so logging never waits on the disk
+// mid-rally.
+const EVENT_LOG_FLUSH_FRAMES: u64 = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BallEvent {
+    Paddle,
+    Wall,
+    Score,
+}
+
+impl BallEvent {
+    fn name(self) -> &'static str {
+        match self {
+            BallEvent::Paddle => "paddle",
+            BallEvent::Wall => "wall",
+            BallEvent::Score => "score",
+        }
+    }
+}
+
+fn event_row(frame: u64, event: BallEvent, position: Vec2<f32>, speed: f32) -> String {
+    format!(
+        "{},{},{:.1},{:.1},{:.1}",
+        frame,
+        event.name(),
+        position.x,
+        position.y,
+        speed
+    )
+}
+
+// `--log-events`: a row for every paddle hit, wall bounce and point, added
+// to the end of the file. Rows are buffered, and whatever is left is written
+// when the game closes.
+pub struct EventLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl EventLog {
+    pub fn open(path: &Path) -> io::Result<EventLog> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "frame,event,x,y,speed")?;
+        }
+
+        Ok(EventLog {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+        })
+    }
+
+    fn record(&mut self, row: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", row)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            eprintln!("Could not write events to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+// Network play: the host runs the match and sends its state every tick, and
+// the client sends its paddle input and draws whatever it was last sent. Each
+// message is a big-endian u32 length followed by that many bytes: a tag, then
+// the message's fields in order, also big-endian.
+const NET_INPUT: u8 = 1;
+const NET_STATE: u8 = 2;
+const NET_HELLO: u8 = 3;
+// A longer frame means the stream is broken rather than still arriving.
+const NET_MAX_MESSAGE: usize = 4096;
+// Unsent bytes allowed to pile up before the peer is counted as gone.
+const NET_MAX_BACKLOG: usize = 64 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NetRole {
+    Host,
+    Client,
+    // Watches the host's match without a paddle.
+    Spectator,
+}
+
+// The client's paddle, steered on the host as player 2.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+struct RemoteInput {
+    direction: f32,
+    serve: bool,
+    dash: bool,
+}
+
+impl RemoteInput {
+    fn apply(&self, input: &mut FrameInput) {
+        input.player2 = self.direction.clamp(-1.0, 1.0);
+        input.serve2 = self.serve;
+        input.dash[1] = self.dash;
+    }
+}
+
+// What the client needs to draw a frame of the host's match.
+#[derive(Clone, PartialEq, Debug, Default)]
+struct NetState {
+    tick: u32,
+    player1: Vec2<f32>,
+    player2: Vec2<f32>,
+    player1_height_scale: f32,
+    player2_height_scale: f32,
+    // The host's timers, so the client shows the same countdowns and flashes.
+    point_flash: f32,
+    serve_countdown: f32,
+    intro_countdown: f32,
+    balls: Vec<Vec2<f32>>,
+    player1_score: u32,
+    player2_score: u32,
+    // 0 while the match is on, then 1 or 2 for the winner, or 3 for a draw.
+    winner: u8,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum NetMessage {
+    // The first thing a client or spectator sends, to say which it is.
+    Hello { spectator: bool },
+    Input(RemoteInput),
+    State(NetState),
+}
+
+impl NetMessage {
+    // The whole frame, length prefix included.
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+
+        match self {
+            NetMessage::Hello { spectator } => {
+                body.push(NET_HELLO);
+                body.push(*spectator as u8);
+            }
+            NetMessage::Input(input) => {
+                body.push(NET_INPUT);
+                body.extend(input.direction.to_be_bytes());
+                body.push(input.serve as u8);
+                body.push(input.dash as u8);
+            }
+            NetMessage::State(state) => {
+                body.push(NET_STATE);
+                body.extend(state.tick.to_be_bytes());
+                for value in [
+                    state.player1.x,
+                    state.player1.y,
+                    state.player2.x,
+                    state.player2.y,
+                    state.player1_height_scale,
+                    state.player2_height_scale,
+                    state.point_flash,
+                    state.serve_countdown,
+                    state.intro_countdown,
+                ] {
+                    body.extend(value.to_be_bytes());
+                }
+                let balls = &state.balls[..state.balls.len().min(u8::MAX as usize)];
+                body.push(balls.len() as u8);
+                for ball in balls {
+                    body.extend(ball.x.to_be_bytes());
+                    body.extend(ball.y.to_be_bytes());
+                }
+                body.extend(state.player1_score.to_be_bytes());
+                body.extend(state.player2_score.to_be_bytes());
+                body.push(state.winner);
+            }
+        }
+
+        let mut frame = (body.len() as u32).to_be_bytes().to_vec();
+        frame.extend(body);
+        frame
+    }
+
+    fn decode(body: &[u8]) -> Result<NetMessage, String> {
+        let mut reader = NetReader { bytes: body };
+
+        let message = match reader.u8()? {
+            NET_HELLO => NetMessage::Hello {
+                spectator: reader.u8()? != 0,
+            },
+            NET_INPUT => NetMessage::Input(RemoteInput {
+                direction: reader.f32()?,
+                serve: reader.u8()? != 0,
+                dash: reader.u8()? != 0,
+            }),
+            NET_STATE => {
+                let tick = reader.u32()?;
+                let player1 = reader.vec2()?;
+                let player2 = reader.vec2()?;
+                let player1_height_scale = reader.f32()?;
+                let player2_height_scale = reader.f32()?;
+                let point_flash = reader.f32()?;
+                let serve_countdown = reader.f32()?;
+                let intro_countdown = reader.f32()?;
+                let balls = (0..reader.u8()?)
+                    .map(|_| reader.vec2())
+                    .collect::<Result<_, _>>()?;
+
+                NetMessage::State(NetState {
+                    tick,
+                    player1,
+                    player2,
+                    player1_height_scale,
+                    player2_height_scale,
+                    point_flash,
+                    serve_countdown,
+                    intro_countdown,
+                    balls,
+                    player1_score: reader.u32()?,
+                    player2_score: reader.u32()?,
+                    winner: reader.u8()?,
+                })
+            }
+            tag => return Err(format!("unknown message type {}", tag)),
+        };
+
+        if !reader.bytes.is_empty() {
+            return Err("message has trailing bytes".to_string());
+        }
+
+        Ok(message)
+    }
+}
+
+struct NetReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl NetReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        if self.bytes.len() < N {
+            return Err("message is cut short".to_string());
+        }
+
+        let (taken, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(taken.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take()?))
+    }
+
+    // NaN or infinity would spread through the physics for the rest of the
+    // match, so a peer that sends one is treated as broken.
+    fn f32(&mut self) -> Result<f32, String> {
+        let value = f32::from_be_bytes(self.take()?);
+        if !value.is_finite() {
+            return Err("message has a non-finite number".to_string());
+        }
+
+        Ok(value)
+    }
+
+    fn vec2(&mut self) -> Result<Vec2<f32>, String> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+}
+
+// Removes every complete frame from the front of `buffer`, leaving any
+// partial one to be finished by the next read.
+fn take_messages(buffer: &mut Vec<u8>) -> Result<Vec<NetMessage>, String> {
+    let mut messages = Vec::new();
+    let mut start = 0;
+
+    while buffer.len() - start >= 4 {
+        let length = u32::from_be_bytes(buffer[start..start + 4].try_into().unwrap()) as usize;
+
+        if length > NET_MAX_MESSAGE {
+            return Err(format!("message of {} bytes is too long", length));
+        }
+
+        let end = start + 4 + length;
+        if buffer.len() < end {
+            break;
+        }
+
+        messages.push(NetMessage::decode(&buffer[start + 4..end])?);
+        start = end;
+    }
+
+    buffer.drain(..start);
+    Ok(messages)
+}
+
+// One end of a connection. The stream never blocks, so reads and writes can
+// happen once per update.
+struct NetPeer {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl NetPeer {
+    fn new(stream: TcpStream) -> io::Result<NetPeer> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(NetPeer {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    // Queues the message and writes as much as the socket will take.
+    fn send(&mut self, message: &NetMessage) -> Result<(), String> {
+        self.outgoing.extend(message.encode());
+
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err("connection closed".to_string()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+
+        if self.outgoing.len() > NET_MAX_BACKLOG {
+            return Err("the other end stopped responding".to_string());
+        }
+
+        Ok(())
+    }
+
+    // Everything that has fully arrived since the last call.
+    fn receive(&mut self) -> Result<Vec<NetMessage>, String> {
+        let mut chunk = [0; 1024];
+
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err("connection closed".to_string()),
+                Ok(read) => self.incoming.extend(&chunk[..read]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+
+        take_messages(&mut self.incoming)
+    }
+}
+
+// The host's listening socket. New connections wait in `pending` until
+// their hello says whether they've come to play or to watch.
+struct Lobby {
+    listener: TcpListener,
+    pending: Vec<NetPeer>,
+    spectators: Vec<NetPeer>,
+}
+
+impl Lobby {
+    // Seats any new spectators, and returns the first new player.
+    fn admit(&mut self) -> Option<NetPeer> {
+        while let Ok((stream, address)) = self.listener.accept() {
+            match NetPeer::new(stream) {
+                Ok(peer) => self.pending.push(peer),
+                Err(e) => eprintln!("Could not accept {}: {}", address, e),
+            }
+        }
+
+        let mut player = None;
+
+        for mut peer in std::mem::take(&mut self.pending) {
+            match peer.receive().map(|messages| messages.into_iter().next()) {
+                Ok(Some(NetMessage::Hello { spectator: true })) => {
+                    eprintln!("A spectator joined");
+                    self.spectators.push(peer);
+                }
+                Ok(Some(NetMessage::Hello { spectator: false })) if player.is_none() => {
+                    player = Some(peer);
+                }
+                Ok(None) => self.pending.push(peer),
+                Ok(Some(_)) => eprintln!("Turned away a connection"),
+                Err(e) => eprintln!("Dropped a connection: {}", e),
+            }
+        }
+
+        player
+    }
+}
+
+// A connection to the other machine, and for the host its spectators.
+pub struct NetLink {
+    role: NetRole,
+    peer: NetPeer,
+    lobby: Option<Lobby>,
+}
+
+impl NetLink {
+    // Waits for a player to join before the window opens. Spectators can
+    // join then or at any point during the match.
+    pub fn host(port: u16) -> io::Result<NetLink> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        eprintln!("Waiting for a player on port {}...", port);
+
+        let mut lobby = Lobby {
+            listener,
+            pending: Vec::new(),
+            spectators: Vec::new(),
+        };
+        let peer = loop {
+            match lobby.admit() {
+                Some(peer) => break peer,
+                None => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+        eprintln!("A player joined");
+
+        Ok(NetLink {
+            role: NetRole::Host,
+            peer,
+            lobby: Some(lobby),
+        })
+    }
+
+    pub fn connect(address: SocketAddr, role: NetRole) -> io::Result<NetLink> {
+        let mut peer = NetPeer::new(TcpStream::connect(address)?)?;
+        let hello = NetMessage::Hello {
+            spectator: role == NetRole::Spectator,
+        };
+        peer.send(&hello).map_err(io::Error::other)?;
+
+        Ok(NetLink {
+            role,
+            peer,
+            lobby: None,
+        })
+    }
+
+    fn send(&mut self, message: &NetMessage) -> Result<(), String> {
+        self.peer.send(message)
+    }
+
+    fn receive(&mut self) -> Result<Vec<NetMessage>, String> {
+        self.peer.receive()
+    }
+
+    // Seats new spectators; a second player is turned away.
+    fn admit(&mut self) {
+        if let Some(lobby) = &mut self.lobby {
+            if lobby.admit().is_some() {
+                eprintln!("Turned away a second player");
+            }
+        }
+    }
+
+    // Only losing the player ends the match; spectators just drop out.
+    fn broadcast(&mut self, message: &NetMessage) -> Result<(), String> {
+        if let Some(lobby) = &mut self.lobby {
+            lobby
+                .spectators
+                .retain_mut(|spectator| match spectator.send(message) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        eprintln!("A spectator left: {}", e);
+                        false
+                    }
+                });
+        }
+
+        self.peer.send(message)
+    }
+}
+
+// How far the client's own paddle may run ahead of the host's copy before it's
+// pulled back, and how much of the gap closes per state once it stops.
+const NET_RECONCILE_DISTANCE: f32 = 120.0;
+const NET_RECONCILE_RATE: f32 = 0.2;
+// Snapshots the client keeps; drawing only needs the newest two.
+const SNAPSHOT_CAPACITY: usize = 4;
+
+// The client's paddle moves as soon as its keys do. When the host's state
+// arrives, the prediction is kept unless it has clearly gone wrong.
+fn reconcile(predicted: f32, authoritative: f32, moving: bool) -> f32 {
+    let error = authoritative - predicted;
+
+    if error.abs() > NET_RECONCILE_DISTANCE {
+        authoritative - error.signum() * NET_RECONCILE_DISTANCE
+    } else if !moving {
+        predicted + error * NET_RECONCILE_RATE
+    } else {
+        predicted
+    }
+}
+
+// The host's latest states on the client, oldest first by tick. The client
+// draws between the newest two, one snapshot behind the host, so the remote
+// paddle and the ball move smoothly however the states arrive.
+#[derive(Default)]
+struct SnapshotBuffer {
+    snapshots: VecDeque<NetState>,
+    // Client ticks since the newest snapshot arrived.
+    age: f32,
+}
+
+impl SnapshotBuffer {
+    // States at or before the newest tick are stale and dropped.
+    fn push(&mut self, state: NetState) -> bool {
+        if self
+            .snapshots
+            .back()
+            .is_some_and(|newest| state.tick <= newest.tick)
+        {
+            return false;
+        }
+
+        if self.snapshots.len() == SNAPSHOT_CAPACITY {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(state);
+        self.age = 0.0;
+        true
+    }
+
+    fn advance(&mut self) {
+        self.age += 1.0;
+    }
+
+    // A rematch restarts the host's ticks, so the last match's are forgotten.
+    fn clear(&mut self) {
+        self.snapshots.clear();
+        self.age = 0.0;
+    }
+
+    // The newest state with its positions blended in from the one before, by
+    // how far the client has got through the gap between them. Falls back to
+    // the newest state on its own when ball counts differ.
+    fn sample(&self, blend: f32) -> Option<NetState> {
+        let newest = self.snapshots.back()?;
+        let Some(older) = self.snapshots.iter().rev().nth(1) else {
+            return Some(newest.clone());
+        };
+        let gap = newest.tick.wrapping_sub(older.tick).max(1) as f32;
+        let t = ((self.age + blend) / gap).clamp(0.0, 1.0);
+        let mut state = newest.clone();
+
+        state.player1 = Vec2::lerp(older.player1, newest.player1, t);
+        state.player2 = Vec2::lerp(older.player2, newest.player2, t);
+        if older.balls.len() == newest.balls.len() {
+            for (ball, &from) in state.balls.iter_mut().zip(&older.balls) {
+                *ball = Vec2::lerp(from, *ball, t);
+            }
+        }
+
+        Some(state)
+    }
+}
+
+// A match in progress, written by F5 and read back by F9. Not persisted, as
+// they are either rebuilt on load or purely cosmetic:
+// - textures, fonts, texts and sounds
+// - particles, the ball trail, court shake and hitstop
+// - the AI controllers' state and any serve charge
+// - pause, debug overlays, the menu selection and the attract demo
+// - any replay being recorded or played back
+// Fields added after the first saves were written default when missing.
+#[derive(Serialize, Deserialize)]
+struct SaveData {
+    scene: Scene,
+    winner: String,
+    #[serde(default)]
+    four_player: bool,
+    player2_control: ControlMode,
+    #[serde(default)]
+    extra_control: ControlMode,
+    difficulty: Difficulty,
+    court: Court,
+    match_mode: MatchMode,
+    #[serde(default)]
+    orientation: Orientation,
+    player1_score: u32,
+    player2_score: u32,
+    #[serde(default)]
+    player3_score: u32,
+    #[serde(default)]
+    player4_score: u32,
+    games_won_p1: u32,
+    games_won_p2: u32,
+    rally_hits: u32,
+    longest_rally: u32,
+    player1: EntityState,
+    player2: EntityState,
+    #[serde(default)]
+    player3: EntityState,
+    #[serde(default)]
+    player4: EntityState,
+    balls: Vec<EntityState>,
+    point_flash: f32,
+    point_message: String,
+    serve_countdown: f32,
+    #[serde(default)]
+    intro_countdown: f32,
+    match_timer: f32,
+    time_scale: f32,
+    power_up: Option<PowerUp>,
+    power_up_timer: f32,
+    last_hitter: Option<Side>,
+    #[serde(default)]
+    last_touch: Option<Edge>,
+    player1_resize_time: f32,
+    player2_resize_time: f32,
+    #[serde(default)]
+    player1_shield_active: bool,
+    #[serde(default)]
+    player2_shield_active: bool,
+    obstacle: Rectangle,
+    obstacle_velocity: f32,
+    bricks: Vec<Brick>,
+    bricks_p1: u32,
+    bricks_p2: u32,
+    stats: MatchStats,
+}
+
+// Something a tick of the match did that the game shows, plays or logs, in
+// the order it happened.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SimEvent {
+    // A paddle sent the ball back, making the rally `rally` hits long.
+    PaddleHit {
+        edge: Edge,
+        position: Vec2<f32>,
+        speed: f32,
+        rally: u32,
+    },
+    Bounce {
+        surface: Surface,
+        position: Vec2<f32>,
+        speed: f32,
+    },
+    PowerUp(Vec2<f32>),
+    // A ball left the court, ending the point.
+    Out {
+        position: Vec2<f32>,
+        speed: f32,
+    },
+    // The message to flash before the next serve.
+    Point(&'static str),
+    // A game was won, which counts towards the records.
+    GameWon,
+    // Nobody is left to beat the winner, if there is one.
+    MatchOver(Option<Edge>),
+}
+
+// What the ball bounced off, other than a paddle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Surface {
+    Wall,
+    Brick,
+    Obstacle,
+    Shield,
+}
+
+// The match itself: everything the rules move and score, with nothing that
+// needs a window. `GameState` drives it a tick at a time and shows the result.
+struct Simulation {
+    // The settings the match started with. Only the rules are read from here.
+    settings: Settings,
+    // The court, in court coordinates.
+    size: Vec2<f32>,
+    // Every random decision in the match draws from this one generator, which
+    // `start` reseeds so the match can be replayed from its seed alone.
+    // Consumers, in call order within a tick:
+    // - `update_power_up_spawner` when a power-up appears: one u32 and one f32
+    // - `random_serve_velocity` in `reset_ball`: one bool and one f32 per serve
+    rng: StdRng,
+    court: Court,
+    match_mode: MatchMode,
+    match_timer: f32,
+    obstacle: Rectangle,
+    obstacle_velocity: f32,
+    bricks: Vec<Brick>,
+    bricks_p1: u32,
+    bricks_p2: u32,
+    player2_control: ControlMode,
+    difficulty: Difficulty,
+    player1_ai: AiController,
+    player2_ai: AiController,
+    // Four-player mode adds players 3 and 4 guarding the top and bottom
+    // edges. Whoever last touched the ball scores when it leaves through
+    // someone else's edge, and the first to the winning score takes the match.
+    four_player: bool,
+    extra_control: ControlMode,
+    player3: Entity,
+    player4: Entity,
+    player3_ai: AiController,
+    player4_ai: AiController,
+    player3_score: u32,
+    player4_score: u32,
+    last_touch: Option<Edge>,
+    // Attract mode: a CPU-vs-CPU match that starts after idling on the menu
+    // and never touches the saved records.
+    demo: bool,
+    player1: Entity,
+    player2: Entity,
+    balls: Vec<Entity>,
+    player1_score: u32,
+    player2_score: u32,
+    rally_hits: u32,
+    longest_rally: u32,
+    games_won_p1: u32,
+    games_won_p2: u32,
+    stats: MatchStats,
+    serve_charge: f32,
+    point_flash: f32,
+    serve_countdown: f32,
+    // The "Get Ready" wait before a match's first serve, which takes the place
+    // of that serve's own countdown.
+    intro_countdown: f32,
+    last_mouse_y: f32,
+    // Whether the mouse placed player 1 last update. Its measured speed was
+    // only for the spin on that update's hits, so it mustn't coast on it.
+    player1_mouse_steered: bool,
+    ball_trail: VecDeque<Vec2<f32>>,
+    hitstop_frames: u32,
+    time_scale: f32,
+    power_up: Option<PowerUp>,
+    power_up_timer: f32,
+    last_hitter: Option<Side>,
+    player1_resize_time: f32,
+    player2_resize_time: f32,
+    player1_shield_active: bool,
+    player2_shield_active: bool,
+    heatmap: Heatmap,
+    // What happened this tick, handed over by `step`.
+    events: Vec<SimEvent>,
+}
+
+impl Simulation {
+    // The paddles are sized as player 1, player 2 and the top and bottom
+    // paddles.
+    fn new(
+        settings: Settings,
+        size: Vec2<f32>,
+        paddle_sizes: [Vec2<f32>; 4],
+        ball_size: Vec2<f32>,
+        mut rng: StdRng,
+    ) -> Simulation {
+        let Vec2 {
+            x: width,
+            y: height,
+        } = size;
+        let [player1_size, player2_size, player3_size, player4_size] = paddle_sizes;
+        let ball_velocity = random_serve_velocity(&mut rng, settings.ball_speed);
+
+        Simulation {
+            settings,
+            size,
+            rng,
+            court: Court::Open,
+            match_mode: MatchMode::Points,
+            match_timer: settings.match_duration,
+            obstacle: centred_obstacle(width, height),
+            obstacle_velocity: 0.0,
+            bricks: build_bricks(width, height),
+            bricks_p1: 0,
+            bricks_p2: 0,
+            player2_control: ControlMode::Human,
+            difficulty: Difficulty::Medium,
+            player1_ai: AiController::default(),
+            player2_ai: AiController::default(),
+            four_player: false,
+            extra_control: ControlMode::Human,
+            player3: Entity::paddle(
+                player3_size,
+                Vec2::new((width - player3_size.x) / 2.0, PADDLE_MARGIN),
+                settings.paddle_speed,
+                1.0,
+            ),
+            player4: Entity::paddle(
+                player4_size,
+                Vec2::new(
+                    (width - player4_size.x) / 2.0,
+                    height - player4_size.y - PADDLE_MARGIN,
+                ),
+                settings.paddle_speed,
+                1.0,
+            ),
+            player3_ai: AiController::default(),
+            player4_ai: AiController::default(),
+            player3_score: 0,
+            player4_score: 0,
+            last_touch: None,
+            demo: false,
+            player1: Entity::paddle(
+                player1_size,
+                Vec2::new(
+                    PADDLE_MARGIN,
+                    (height - player1_size.y * settings.player1_paddle_size) / 2.0,
+                ),
+                settings
+                    .player1_paddle_speed
+                    .unwrap_or(settings.paddle_speed),
+                settings.player1_paddle_size,
+            ),
+            player2: Entity::paddle(
+                player2_size,
+                Vec2::new(
+                    width - player2_size.x - PADDLE_MARGIN,
+                    (height - player2_size.y * settings.player2_paddle_size) / 2.0,
+                ),
+                settings
+                    .player2_paddle_speed
+                    .unwrap_or(settings.paddle_speed),
+                settings.player2_paddle_size,
+            ),
+            balls: vec![Entity::with_velocity(
+                ball_size,
+                (size - ball_size) / 2.0,
+                ball_velocity,
+            )],
+            player1_score: 0,
+            player2_score: 0,
+            rally_hits: 0,
+            longest_rally: 0,
+            games_won_p1: 0,
+            games_won_p2: 0,
+            stats: MatchStats::default(),
+            serve_charge: 0.0,
+            point_flash: 0.0,
+            serve_countdown: 0.0,
+            intro_countdown: 0.0,
+            last_mouse_y: 0.0,
+            player1_mouse_steered: false,
+            ball_trail: VecDeque::with_capacity(TRAIL_LENGTH),
+            hitstop_frames: 0,
+            time_scale: 1.0,
+            power_up: None,
+            power_up_timer: POWER_UP_INTERVAL,
+            last_hitter: None,
+            player1_resize_time: 0.0,
+            player2_resize_time: 0.0,
+            player1_shield_active: false,
+            player2_shield_active: false,
+            heatmap: Heatmap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    // Everything a match depends on is reset here, so its seed and inputs are
+    // enough to replay it.
+    fn start(&mut self, seed: u64) {
+        let Vec2 {
+            x: width,
+            y: height,
+        } = self.size;
+
+        self.rng = StdRng::seed_from_u64(seed);
+        self.stats = MatchStats::default();
+        self.heatmap = Heatmap::new();
+        self.match_timer = self.settings.match_duration;
+        self.player1_score = 0;
+        self.player2_score = 0;
+        self.player3_score = 0;
+        self.player4_score = 0;
+        self.games_won_p1 = 0;
+        self.games_won_p2 = 0;
+        self.rally_hits = 0;
+        self.longest_rally = 0;
+        self.point_flash = 0.0;
+        self.hitstop_frames = 0;
+        self.player1_ai.reset();
+        self.player2_ai.reset();
+        self.player3_ai.reset();
+        self.player4_ai.reset();
+        self.power_up = None;
+        self.power_up_timer = POWER_UP_INTERVAL;
+        self.player1_resize_time = 0.0;
+        self.player2_resize_time = 0.0;
+        self.player1_shield_active = false;
+        self.player2_shield_active = false;
+        self.player1.set_height_scale(1.0);
+        self.player2.set_height_scale(1.0);
+        self.player1.position.y = (height - self.player1.height()) / 2.0;
+        self.player2.position.y = (height - self.player2.height()) / 2.0;
+        self.player3.position.x = (width - self.player3.width()) / 2.0;
+        self.player4.position.x = (width - self.player4.width()) / 2.0;
+        for paddle in [
+            &mut self.player1,
+            &mut self.player2,
+            &mut self.player3,
+            &mut self.player4,
+        ] {
+            paddle.velocity = Vec2::zero();
+            paddle.effects.clear();
+            paddle.settle();
+        }
+        self.obstacle = centred_obstacle(width, height);
+        self.obstacle_velocity = 0.0;
+        self.bricks = build_bricks(width, height);
+        self.bricks_p1 = 0;
+        self.bricks_p2 = 0;
+        self.serve_charge = 0.0;
+        self.player1_mouse_steered = false;
+        self.events.clear();
+        self.reset_ball();
+        self.serve_countdown = 0.0;
+        self.intro_countdown = MATCH_INTRO_COUNTDOWN;
+    }
+
+    // The paddles take their speeds and handicap sizes from the settings when
+    // they're made; this brings them in line after the settings change.
+    fn apply_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+        for (paddle, speed, size_scale) in [
+            (
+                &mut self.player1,
+                settings.player1_paddle_speed,
+                settings.player1_paddle_size,
+            ),
+            (
+                &mut self.player2,
+                settings.player2_paddle_speed,
+                settings.player2_paddle_size,
+            ),
+            (&mut self.player3, None, 1.0),
+            (&mut self.player4, None, 1.0),
+        ] {
+            paddle.speed = speed.unwrap_or(settings.paddle_speed);
+            paddle.size_scale = size_scale;
+        }
+    }
+
+    // Keeps the paddles at their margins and everything inside the court after
+    // its size changes.
+    fn fit(&mut self, size: Vec2<f32>) {
+        self.size = size;
+        let Vec2 {
+            x: width,
+            y: height,
+        } = size;
+
+        self.player1.position.x = PADDLE_MARGIN;
+        self.player2.position.x = width - self.player2.width() - PADDLE_MARGIN;
+        self.obstacle.x = (width - self.obstacle.width) / 2.0;
+
+        for (index, brick) in self.bricks.iter_mut().enumerate() {
+            brick.bounds = brick_bounds(index, width, height);
+        }
+
+        self.obstacle.y = self.obstacle.y.clamp(
+            OBSTACLE_MARGIN,
+            (height - OBSTACLE_MARGIN - self.obstacle.height).max(OBSTACLE_MARGIN),
+        );
+        self.player1
+            .clamp_vertical(0.0, height - self.player1.height());
+        self.player2
+            .clamp_vertical(0.0, height - self.player2.height());
+        self.player4.position.y = height - self.player4.height() - PADDLE_MARGIN;
+        self.player3
+            .clamp_horizontal(0.0, width - self.player3.width());
+        self.player4
+            .clamp_horizontal(0.0, width - self.player4.width());
+
+        // A ball that has already left the court is about to be re-served.
+        if self.point_flash <= 0.0 {
+            for ball in &mut self.balls {
+                ball.position.x = ball.position.x.clamp(0.0, width - ball.width());
+                ball.clamp_vertical(0.0, height - ball.height());
+            }
+        }
+
+        self.settle_entities();
+    }
+
+    // Advances the match by one tick of `dt` seconds on that tick's input, and
+    // returns what happened. Nothing here reads the window or the clock, so
+    // the same seed and inputs always play out the same match.
+    fn step(&mut self, input: &FrameInput, dt: f32) -> Vec<SimEvent> {
+        self.update(input, dt);
+        std::mem::take(&mut self.events)
+    }
+
+    fn update(&mut self, input: &FrameInput, dt: f32) {
+        if self.hitstop_frames > 0 {
+            self.hitstop_frames -= 1;
+            return;
+        }
+
+        self.update_resize_effects(dt);
+        self.update_status_effects(dt);
+        self.stats.duration += dt;
+
+        // Practice has no opponent to beat, so it is never on the clock. Nor is
+        // anyone while they're getting ready.
+        if self.match_mode == MatchMode::Timed
+            && self.player2_control != ControlMode::Wall
+            && self.intro_countdown <= 0.0
+        {
+            self.match_timer -= dt;
+
+            if self.match_timer <= 0.0 {
+                self.end_timed_match();
+                return;
+            }
+        }
+
+        let paddles_before = (self.player1.position.y, self.player2.position.y);
+        let height = self.size.y;
+
+        if self.demo {
+            self.update_ai_paddle(Side::Left, height, dt);
+        } else {
+            if self.player1_mouse_steered {
+                self.player1.velocity.y = 0.0;
+            }
+            self.player1.steer(input.player1, input.dash[0], false, dt);
+            self.player1_mouse_steered = false;
+
+            // The mouse only takes over while it is moving, so W/S keep working.
+            // A reversed paddle follows the mouse mirrored about the middle.
+            if let Some(mouse_y) = input.mouse_y {
+                let target_y = if self.player1.has_effect(EffectKind::Reverse) {
+                    height - mouse_y
+                } else {
+                    mouse_y
+                };
+
+                if !self.player1.has_effect(EffectKind::Freeze) {
+                    self.player1.position.y = target_y - self.player1.height() / 2.0;
+                    self.player1_mouse_steered = true;
+                }
+                self.last_mouse_y = mouse_y;
+            }
+
+            if input.next_court {
+                self.court = self.court.next();
+            }
+        }
+
+        if input.toggle_ai {
+            self.player2_control = match self.player2_control {
+                ControlMode::Human => ControlMode::Ai,
+                ControlMode::Ai => ControlMode::Human,
+                ControlMode::Wall => ControlMode::Wall,
+            };
+        }
+
+        match self.player2_control {
+            ControlMode::Human => {
+                self.player2.steer(input.player2, input.dash[1], false, dt);
+            }
+            ControlMode::Ai => self.update_ai_paddle(Side::Right, height, dt),
+            ControlMode::Wall => {}
+        }
+
+        if self.four_player {
+            self.update_extra_paddles(input, dt);
+        }
+
+        self.player1
+            .clamp_vertical(0.0, height - self.player1.height());
+        self.player2
+            .clamp_vertical(0.0, height - self.player2.height());
+
+        // How fast each side paddle actually moved, for the spin it puts on
+        // the ball. A paddle stopped by the wall loses its momentum here too.
+        if dt > 0.0 {
+            self.player1.velocity.y = (self.player1.position.y - paddles_before.0) / dt;
+            self.player2.velocity.y = (self.player2.position.y - paddles_before.1) / dt;
+        }
+
+        if self.point_flash > 0.0 {
+            self.point_flash -= dt;
+
+            if self.point_flash <= 0.0 {
+                self.reset_ball();
+            }
+
+            return;
+        }
+
+        // The paddles are already free to move; the ball waits in the middle.
+        if self.intro_countdown > 0.0 {
+            self.intro_countdown -= dt;
+            return;
+        }
+
+        if self.serve_countdown > 0.0 {
+            self.serve_countdown -= dt;
+            self.update_serve_charge(input, dt);
+            return;
+        }
+
+        for ball in &self.balls {
+            self.heatmap.record(ball.centre(), self.size);
+        }
+
+        self.update_power_up_spawner(dt);
+        self.update_obstacle(dt);
+
+        // Slow motion only affects the ball and eases back to full speed.
+        self.time_scale =
+            (self.time_scale + (1.0 - NEAR_MISS_TIME_SCALE) / NEAR_MISS_RECOVERY * dt).min(1.0);
+
+        // Any ball leaving the court ends the point. It is kept (off court,
+        // as in single-ball play) until the re-serve, and the rest vanish.
+        for index in 0..self.balls.len() {
+            if let Some(edge) = self.update_ball(index, input, dt) {
+                self.balls.swap(0, index);
+                self.balls.truncate(1);
+                self.events.push(SimEvent::Out {
+                    position: self.balls[0].centre(),
+                    speed: self.balls[0].velocity.magnitude(),
+                });
+
+                if self.player2_control == ControlMode::Wall {
+                    self.miss_practice_ball();
+                } else if self.four_player {
+                    self.award_four_player_point(edge);
+                } else if let Some(side) = edge.scorer() {
+                    self.award_point(side);
+                }
+
+                return;
+            }
+        }
+    }
+
+    fn reset_ball(&mut self) {
+        self.balls.truncate(1);
+
+        let Vec2 {
+            x: width,
+            y: height,
+        } = self.size;
+
+        // A cleared wall of bricks is rebuilt for the next serve.
+        if self.bricks.iter().all(|brick| !brick.alive) {
+            self.bricks = build_bricks(width, height);
+        }
+
+        // With something in the middle of the court, serve from the larger
+        // gap beside it.
+        let blocker = if self.court.has_obstacle() {
+            Some(self.obstacle)
+        } else if self.court == Court::Bricks {
+            Some(brick_grid(width, height))
+        } else {
+            None
+        };
+
+        let serve_y = match blocker {
+            Some(blocker) => {
+                let below = blocker.y + blocker.height;
+
+                if blocker.y > height - below {
+                    blocker.y / 2.0
+                } else {
+                    (below + height) / 2.0
+                }
+            }
+            None => height / 2.0,
+        };
+
+        let ball = &mut self.balls[0];
+        ball.position = Vec2::new(
+            width / 2.0 - ball.width() / 2.0,
+            serve_y - ball.height() / 2.0,
+        );
+        ball.velocity = random_serve_velocity(&mut self.rng, self.settings.ball_speed);
+        ball.rotation = 0.0;
+        ball.spin = 0.0;
+        ball.caught_by = None;
+        ball.settle();
+        self.serve_countdown = SERVE_COUNTDOWN;
+        self.serve_charge = 0.0;
+        self.ball_trail.clear();
+        self.time_scale = 1.0;
+        self.last_hitter = None;
+        self.last_touch = None;
+    }
+
+    fn settle_entities(&mut self) {
+        for paddle in [
+            &mut self.player1,
+            &mut self.player2,
+            &mut self.player3,
+            &mut self.player4,
+        ] {
+            paddle.settle();
+        }
+        for ball in &mut self.balls {
+            ball.settle();
+        }
+    }
+
+    fn award_point(&mut self, side: Side) {
+        let (score, games_won, edge) = match side {
+            Side::Left => (&mut self.player1_score, &mut self.games_won_p1, Edge::Left),
+            Side::Right => (&mut self.player2_score, &mut self.games_won_p2, Edge::Right),
+        };
+
+        *score += 1;
+        let status = game_status(
+            self.player1_score,
+            self.player2_score,
+            self.settings.win_score,
+        );
+        self.rally_hits = 0;
+
+        // Timed matches are decided by the clock, not by winning games.
+        if self.match_mode == MatchMode::Timed || status != GameStatus::Won(side) {
+            self.events.push(SimEvent::Point("POINT!"));
+        } else {
+            *games_won += 1;
+            self.events.push(SimEvent::GameWon);
+
+            if *games_won > SERIES_LENGTH / 2 {
+                self.events.push(SimEvent::MatchOver(Some(edge)));
+                return;
+            }
+
+            self.player1_score = 0;
+            self.player2_score = 0;
+            self.events.push(SimEvent::Point("GAME!"));
+        }
+
+        self.start_point_flash();
+    }
+
+    // Whoever last touched the ball scores, unless it went out through their
+    // own edge or nobody has touched it since the serve.
+    fn award_four_player_point(&mut self, edge: Edge) {
+        self.rally_hits = 0;
+
+        match self.last_touch.filter(|&toucher| toucher != edge) {
+            Some(scorer) => {
+                let score = match scorer {
+                    Edge::Left => &mut self.player1_score,
+                    Edge::Right => &mut self.player2_score,
+                    Edge::Top => &mut self.player3_score,
+                    Edge::Bottom => &mut self.player4_score,
+                };
+                *score += 1;
+
+                if self.match_mode == MatchMode::Points && *score >= self.settings.win_score {
+                    self.events.push(SimEvent::GameWon);
+                    self.events.push(SimEvent::MatchOver(Some(scorer)));
+                    return;
+                }
+
+                self.events.push(SimEvent::Point("POINT!"));
+            }
+            None => self.events.push(SimEvent::Point("NO POINT")),
+        }
+
+        self.start_point_flash();
+    }
+
+    // Each player in the match, with their score.
+    fn player_scores(&self) -> Vec<(Edge, u32)> {
+        let mut scores = vec![
+            (Edge::Left, self.player1_score),
+            (Edge::Right, self.player2_score),
+        ];
+
+        if self.four_player {
+            scores.push((Edge::Top, self.player3_score));
+            scores.push((Edge::Bottom, self.player4_score));
+        }
+
+        scores
+    }
+
+    // In practice a miss only ends the rally; nobody scores.
+    fn miss_practice_ball(&mut self) {
+        self.rally_hits = 0;
+        self.events.push(SimEvent::Point("MISS!"));
+        self.start_point_flash();
+    }
+
+    fn start_point_flash(&mut self) {
+        self.point_flash = POINT_FLASH_TIME;
+    }
+
+    // The x where a ball's top-left corner sits as it meets this paddle's face.
+    fn contact_x(&self, side: Side, ball: &Entity) -> f32 {
+        match side {
+            Side::Left => self.player1.position.x + self.player1.width(),
+            Side::Right => self.player2.position.x - ball.width(),
+        }
+    }
+
+    // The AI chases whichever ball will reach its paddle first.
+    fn incoming_ball(&self, side: Side) -> Option<&Entity> {
+        let arrival =
+            |ball: &&Entity| (self.contact_x(side, ball) - ball.position.x) / ball.velocity.x;
+
+        self.balls
+            .iter()
+            .filter(|ball| arrival(ball) >= 0.0)
+            .min_by(|a, b| arrival(a).total_cmp(&arrival(b)))
+    }
+
+    // Where the centre of the incoming ball will be when it reaches `side`'s
+    // paddle, after any wall bounces on the way.
+    fn predict_ball_y(&self, side: Side, height: f32) -> f32 {
+        let Some(ball) = self.incoming_ball(side) else {
+            return height / 2.0;
+        };
+
+        predict_intercept_y(
+            ball.position,
+            ball.velocity,
+            ball.height(),
+            self.contact_x(side, ball),
+            height,
+        )
+        .map_or(ball.centre().y, |y| y + ball.height() / 2.0)
+    }
+
+    fn update_ai_paddle(&mut self, side: Side, height: f32, dt: f32) {
+        let Some(ball) = self.incoming_ball(side) else {
+            match side {
+                Side::Left => self.player1_ai.reset(),
+                Side::Right => self.player2_ai.reset(),
+            }
+            return;
+        };
+
+        let aim_offset = if ball.velocity.y > 0.0 {
+            -self.difficulty.aim_error()
+        } else if ball.velocity.y < 0.0 {
+            self.difficulty.aim_error()
+        } else {
+            0.0
+        };
+        let target = self.predict_ball_y(side, height) + aim_offset;
+
+        let (paddle, ai) = match side {
+            Side::Left => (&mut self.player1, &mut self.player1_ai),
+            Side::Right => (&mut self.player2, &mut self.player2_ai),
+        };
+        let max_speed = paddle.speed * self.difficulty.speed_factor();
+
+        if paddle.has_effect(EffectKind::Freeze) {
+            return;
+        }
+
+        let error = target - paddle.centre().y;
+        paddle.position.y += ai.step(error, dt, max_speed) * dt;
+    }
+
+    // Players 3 and 4 move along the top and bottom edges.
+    fn update_extra_paddles(&mut self, input: &FrameInput, dt: f32) {
+        if input.toggle_extra_ai {
+            self.extra_control = match self.extra_control {
+                ControlMode::Human => ControlMode::Ai,
+                _ => ControlMode::Human,
+            };
+        }
+
+        let width = self.size.x;
+
+        if self.extra_control == ControlMode::Ai {
+            self.update_horizontal_ai(Edge::Top, width, dt);
+            self.update_horizontal_ai(Edge::Bottom, width, dt);
+        } else {
+            self.player3.steer(input.player3, input.dash[2], true, dt);
+            self.player4.steer(input.player4, input.dash[3], true, dt);
+        }
+
+        for paddle in [&mut self.player3, &mut self.player4] {
+            let before = paddle.position.x;
+            paddle.clamp_horizontal(0.0, width - paddle.width());
+
+            if paddle.position.x != before {
+                paddle.velocity.x = 0.0;
+            }
+        }
+    }
+
+    // Follows the nearest ball heading for this edge, or drifts back to the
+    // middle when none is.
+    fn update_horizontal_ai(&mut self, edge: Edge, width: f32, dt: f32) {
+        let (paddle, ai) = match edge {
+            Edge::Top => (&mut self.player3, &mut self.player3_ai),
+            _ => (&mut self.player4, &mut self.player4_ai),
+        };
+
+        let distance = |ball: &Entity| match edge {
+            Edge::Top => ball.position.y,
+            _ => paddle.position.y - ball.position.y,
+        };
+        let target = self
+            .balls
+            .iter()
+            .filter(|ball| match edge {
+                Edge::Top => ball.velocity.y < 0.0,
+                _ => ball.velocity.y > 0.0,
+            })
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .map_or(width / 2.0, |ball| ball.centre().x);
+
+        let max_speed = paddle.speed * self.difficulty.speed_factor();
+        let error = target - paddle.centre().x;
+        paddle.position.x += ai.step(error, dt, max_speed) * dt;
+    }
+
+    // Moves one ball and resolves its collisions. Returns the edge the ball
+    // left the court through, if it did.
+    fn update_ball(&mut self, index: usize, input: &FrameInput, dt: f32) -> Option<Edge> {
+        // Taken out of the list so it can be updated alongside the rest of
+        // the state, then put back.
+        let mut ball = self.balls.remove(index);
+
+        // A caught ball rides along with its paddle until the serve key is
+        // let go, then launches with whatever spin the paddle's motion gives.
+        if let Some(side) = ball.caught_by {
+            let paddle = match side {
+                Side::Left => &self.player1,
+                Side::Right => &self.player2,
+            };
+
+            if input.serve(side) {
+                ball.position.y = paddle.position.y + ball.catch_offset;
+                self.balls.insert(index, ball);
+                return None;
+            }
+
+            let offset = hit_offset(ball.bounds(), paddle.bounds());
+            ball.spin = hit_spin(offset, paddle.velocity.y, ball.velocity.x);
+            ball.caught_by = None;
+        }
+
+        // Four-player matches need vertical speed to reach the top and bottom
+        // goals, and gravity needs it to arc, so it doesn't decay there.
+        if self.settings.gravity {
+            ball.velocity.y += GRAVITY * dt * self.time_scale;
+        } else if !self.four_player {
+            ball.velocity = decay_spin(ball.velocity, dt * self.time_scale);
+        }
+
+        (ball.velocity, ball.spin) = apply_magnus(ball.velocity, ball.spin, dt * self.time_scale);
+
+        let previous_position = ball.position;
+        ball.position += ball.velocity * dt * self.time_scale;
+
+        // Roll the ball as if its vertical motion came from spinning.
+        ball.rotation += ball.velocity.y / (ball.width() / 2.0) * dt * self.time_scale;
+
+        // Only the first ball leaves a trail.
+        if index == 0 {
+            if self.ball_trail.len() == TRAIL_LENGTH {
+                self.ball_trail.pop_front();
+            }
+            self.ball_trail.push_back(previous_position);
+        }
+
+        let ball_size = Vec2::new(ball.width(), ball.height());
+        let sweep_paddle =
+            |paddle: &Entity| sweep(previous_position, ball.position, ball_size, paddle.bounds());
+
+        let paddle_hit = if let Some(t) = sweep_paddle(&self.player1) {
+            Some((&self.player1, Side::Left, t))
+        } else if self.player2_control == ControlMode::Wall {
+            None
+        } else if let Some(t) = sweep_paddle(&self.player2) {
+            Some((&self.player2, Side::Right, t))
+        } else {
+            None
+        };
+
+        let mut hit_paddle = false;
+
+        if let Some((paddle, side, t)) = paddle_hit {
+            if let Some(x) = paddle_contact(ball.width(), ball.velocity.x, paddle.bounds(), side) {
+                hit_paddle = true;
+                self.last_hitter = Some(side);
+                self.last_touch = Some(side.edge());
+                ball.position.x = x;
+                ball.position.y = previous_position.y + (ball.position.y - previous_position.y) * t;
+                let offset = hit_offset(ball.bounds(), paddle.bounds());
+
+                ball.velocity = resolve_paddle_hit(
+                    ball.velocity,
+                    ball.centre(),
+                    paddle.centre(),
+                    paddle.height(),
+                );
+                ball.spin = hit_spin(offset, paddle.velocity.y, ball.velocity.x);
+
+                let human = match side {
+                    Side::Left => !self.demo,
+                    Side::Right => self.player2_control == ControlMode::Human,
+                };
+                if self.settings.catch_assist && human && input.serve(side) {
+                    ball.caught_by = Some(side);
+                    ball.catch_offset = ball.position.y - paddle.position.y;
+                }
+
+                self.hitstop_frames = hitstop_frames(ball.velocity.magnitude());
+
+                match side {
+                    Side::Left => self.stats.hits_p1 += 1,
+                    Side::Right => self.stats.hits_p2 += 1,
+                }
+                self.stats.max_ball_speed =
+                    self.stats.max_ball_speed.max(ball.velocity.magnitude());
+
+                self.rally_hits += 1;
+                self.longest_rally = self.longest_rally.max(self.rally_hits);
+                self.events.push(SimEvent::PaddleHit {
+                    edge: side.edge(),
+                    position: ball.centre(),
+                    speed: ball.velocity.magnitude(),
+                    rally: self.rally_hits,
+                });
+            }
+        }
+
+        if self.four_player && !hit_paddle {
+            self.hit_horizontal_paddles(&mut ball, previous_position);
+        }
+
+        if self.player2_control == ControlMode::Wall {
+            let wall_x = practice_wall(self.size.x, self.size.y).x;
+
+            if ball.position.x + ball.width() >= wall_x && ball.velocity.x > 0.0 {
+                ball.position.x = wall_x - ball.width();
+                ball.velocity.x = -ball.velocity.x;
+                self.bounce(Surface::Wall, &ball);
+            }
+        }
+
+        if self.court == Court::Bricks {
+            self.hit_bricks(&mut ball);
+        }
+
+        if self.court.has_obstacle() {
+            if let Some((position, velocity)) = obstacle_bounce(
+                ball.bounds(),
+                ball.velocity,
+                self.obstacle,
+                self.obstacle_velocity,
+            ) {
+                ball.position = position;
+                ball.velocity = clamp_speed(velocity, MAX_BALL_SPEED);
+                self.bounce(Surface::Obstacle, &ball);
+            }
+        }
+
+        if !hit_paddle {
+            let player1_face = self.player1.position.x + self.player1.width();
+            let player2_face = self.player2.position.x;
+            let ball_right = ball.position.x + ball.width();
+
+            // Check each paddle on the frame the ball slips past its face.
+            let passed = if previous_position.x >= player1_face && ball.position.x < player1_face {
+                Some(&self.player1)
+            } else if previous_position.x + ball.width() <= player2_face
+                && ball_right > player2_face
+            {
+                Some(&self.player2)
+            } else {
+                None
+            };
+
+            if let Some(paddle) = passed {
+                if is_near_miss(ball.bounds(), paddle.bounds(), NEAR_MISS_MARGIN) {
+                    self.time_scale = NEAR_MISS_TIME_SCALE;
+                }
+            }
+        }
+
+        self.collect_power_up(&ball, previous_position);
+        self.hit_shields(&mut ball, previous_position);
+
+        let Vec2 {
+            x: width,
+            y: height,
+        } = self.size;
+        let hit_top = ball.position.y <= 0.0 && ball.velocity.y < 0.0;
+        let hit_bottom = ball.position.y + ball.height() >= height && ball.velocity.y > 0.0;
+
+        // In four-player mode the top and bottom are goals, not walls.
+        if (hit_top || hit_bottom) && !self.four_player {
+            ball.velocity.y = -ball.velocity.y;
+            // Back inside, so a ball dropping under gravity can't sink
+            // through the floor between bounces.
+            ball.position.y = ball.position.y.clamp(0.0, height - ball.height());
+            self.bounce(Surface::Wall, &ball);
+        }
+
+        let exit = if ball.position.x > width {
+            Some(Edge::Right)
+        } else if ball.position.x + ball.width() < 0.0 {
+            Some(Edge::Left)
+        } else if self.four_player && ball.position.y > height {
+            Some(Edge::Bottom)
+        } else if self.four_player && ball.position.y + ball.height() < 0.0 {
+            Some(Edge::Top)
+        } else {
+            None
+        };
+
+        self.balls.insert(index, ball);
+        exit
+    }
+
+    // Each shield sends the ball back once, then it's gone.
+    fn hit_shields(&mut self, ball: &mut Entity, previous_position: Vec2<f32>) {
+        if self.four_player {
+            return;
+        }
+
+        let court = self.size;
+        let ball_size = Vec2::new(ball.width(), ball.height());
+
+        for side in [Side::Left, Side::Right] {
+            let active = match side {
+                Side::Left => &mut self.player1_shield_active,
+                Side::Right => &mut self.player2_shield_active,
+            };
+            let towards_goal = match side {
+                Side::Left => ball.velocity.x < 0.0,
+                Side::Right => ball.velocity.x > 0.0,
+            };
+
+            if !*active || !towards_goal {
+                continue;
+            }
+
+            let shield = shield_bounds(side, court);
+            if sweep(previous_position, ball.position, ball_size, shield).is_none() {
+                continue;
+            }
+
+            *active = false;
+            ball.velocity.x = -ball.velocity.x;
+            ball.position.x = match side {
+                Side::Left => shield.right(),
+                Side::Right => shield.x - ball.width(),
+            };
+
+            self.bounce(Surface::Shield, ball);
+        }
+    }
+
+    // The top and bottom paddles return the ball like the side paddles, with
+    // the axes swapped. They count towards the rally but not the match stats.
+    fn hit_horizontal_paddles(&mut self, ball: &mut Entity, previous_position: Vec2<f32>) {
+        let ball_size = Vec2::new(ball.width(), ball.height());
+
+        for edge in [Edge::Top, Edge::Bottom] {
+            let paddle = match edge {
+                Edge::Top => &self.player3,
+                _ => &self.player4,
+            };
+            let Some(t) = sweep(previous_position, ball.position, ball_size, paddle.bounds())
+            else {
+                continue;
+            };
+            let Some(y) =
+                horizontal_paddle_contact(ball.height(), ball.velocity.y, paddle.bounds(), edge)
+            else {
+                continue;
+            };
+
+            ball.position.x = previous_position.x + (ball.position.x - previous_position.x) * t;
+            ball.position.y = y;
+            ball.velocity = clamp_speed(
+                horizontal_paddle_return(ball.velocity, ball.bounds(), paddle.bounds()),
+                MAX_BALL_SPEED,
+            );
+            // The top and bottom paddles aim by angle alone.
+            ball.spin = 0.0;
+            self.last_hitter = None;
+            self.last_touch = Some(edge);
+
+            self.rally_hits += 1;
+            self.longest_rally = self.longest_rally.max(self.rally_hits);
+            self.events.push(SimEvent::PaddleHit {
+                edge,
+                position: ball.centre(),
+                speed: ball.velocity.magnitude(),
+                rally: self.rally_hits,
+            });
+
+            return;
+        }
+    }
+
+    // Breaks at most one brick per update, credited to whoever last hit the
+    // ball.
+    fn hit_bricks(&mut self, ball: &mut Entity) {
+        let bounce = self
+            .bricks
+            .iter_mut()
+            .filter(|brick| brick.alive)
+            .find_map(|brick| {
+                let bounce = obstacle_bounce(ball.bounds(), ball.velocity, brick.bounds, 0.0)?;
+                brick.alive = false;
+                Some(bounce)
+            });
+
+        let Some((position, velocity)) = bounce else {
+            return;
+        };
+
+        ball.position = position;
+        ball.velocity = velocity;
+
+        match self.last_hitter {
+            Some(Side::Left) => self.bricks_p1 += 1,
+            Some(Side::Right) => self.bricks_p2 += 1,
+            None => {}
+        }
+
+        self.bounce(Surface::Brick, ball);
+    }
+
+    fn update_obstacle(&mut self, dt: f32) {
+        if self.court != Court::MovingObstacle {
+            self.obstacle_velocity = 0.0;
+            return;
+        }
+
+        if self.obstacle_velocity == 0.0 {
+            self.obstacle_velocity = self.settings.obstacle_speed;
+        }
+
+        let max_y = self.size.y - OBSTACLE_MARGIN - self.obstacle.height;
+        self.obstacle.y += self.obstacle_velocity * dt;
+
+        if self.obstacle.y <= OBSTACLE_MARGIN {
+            self.obstacle.y = OBSTACLE_MARGIN;
+            self.obstacle_velocity = self.obstacle_velocity.abs();
+        } else if self.obstacle.y >= max_y {
+            self.obstacle.y = max_y;
+            self.obstacle_velocity = -self.obstacle_velocity.abs();
+        }
+    }
+
+    // The serve goes away from the server, and only human servers can charge.
+    fn charging_server(&self) -> Option<Side> {
+        if self.balls[0].velocity.x > 0.0 {
+            Some(Side::Left)
+        } else if self.player2_control == ControlMode::Human {
+            Some(Side::Right)
+        } else {
+            None
+        }
+    }
+
+    // Holding the serve key during the countdown charges the serve; letting go,
+    // reaching full charge or running out the countdown launches it.
+    fn update_serve_charge(&mut self, input: &FrameInput, dt: f32) {
+        let Some(side) = self.charging_server() else {
+            return;
+        };
+
+        let held = input.serve(side);
+
+        if held {
+            self.serve_charge = (self.serve_charge + dt / SERVE_CHARGE_TIME).min(1.0);
+        }
+
+        let released = !held || self.serve_countdown <= 0.0;
+
+        if (released && self.serve_charge > 0.0) || self.serve_charge >= 1.0 {
+            let ball = &mut self.balls[0];
+            ball.velocity = ball.velocity.normalized()
+                * serve_speed(self.settings.ball_speed, self.serve_charge);
+            self.serve_countdown = 0.0;
+            self.serve_charge = 0.0;
+        }
+    }
+
+    fn update_power_up_spawner(&mut self, dt: f32) {
+        if self.power_up.is_some() {
+            return;
+        }
+
+        self.power_up_timer -= dt;
+
+        if self.power_up_timer <= 0.0 {
+            let Vec2 {
+                x: width,
+                y: height,
+            } = self.size;
+            let kind = match self.rng.gen_range(0..6) {
+                0 => PowerUpKind::Grow,
+                1 => PowerUpKind::Shrink,
+                2 => PowerUpKind::Reverse,
+                3 => PowerUpKind::Freeze,
+                4 => PowerUpKind::Shield,
+                _ => PowerUpKind::MultiBall,
+            };
+            let y = self
+                .rng
+                .gen_range(height * 0.2..height * 0.8 - POWER_UP_SIZE);
+
+            self.power_up = Some(PowerUp {
+                kind,
+                position: Vec2::new((width - POWER_UP_SIZE) / 2.0, y),
+            });
+            self.power_up_timer = POWER_UP_INTERVAL;
+        }
+    }
+
+    // Power-ups are only collected once someone has hit the ball this point.
+    // The whole move is swept, so a fast ball can't pass through one unseen.
+    fn collect_power_up(&mut self, ball: &Entity, previous_position: Vec2<f32>) {
+        let (Some(side), Some(power_up)) = (self.last_hitter, &self.power_up) else {
+            return;
+        };
+
+        let ball_size = Vec2::new(ball.width(), ball.height());
+        if sweep(
+            previous_position,
+            ball.position,
+            ball_size,
+            power_up.bounds(),
+        )
+        .is_none()
+        {
+            return;
+        }
+
+        match power_up.kind {
+            PowerUpKind::Grow | PowerUpKind::Shrink => {
+                let scale = power_up.kind.height_scale();
+                let (paddle, resize_time) = match side {
+                    Side::Left => (&mut self.player1, &mut self.player1_resize_time),
+                    Side::Right => (&mut self.player2, &mut self.player2_resize_time),
+                };
+
+                paddle.set_height_scale(scale);
+                *resize_time = POWER_UP_DURATION;
+            }
+            PowerUpKind::MultiBall => {
+                for angle in [-MULTI_BALL_SPREAD, MULTI_BALL_SPREAD] {
+                    let mut extra = ball.clone();
+                    extra.velocity = rotate(ball.velocity, angle.to_radians());
+                    self.balls.push(extra);
+                }
+            }
+            PowerUpKind::Reverse | PowerUpKind::Freeze => {
+                let kind = match power_up.kind {
+                    PowerUpKind::Reverse => EffectKind::Reverse,
+                    _ => EffectKind::Freeze,
+                };
+                let opponent = match side {
+                    Side::Left => &mut self.player2,
+                    Side::Right => &mut self.player1,
+                };
+
+                opponent.add_effect(kind);
+            }
+            PowerUpKind::Shield => match side {
+                Side::Left => self.player1_shield_active = true,
+                Side::Right => self.player2_shield_active = true,
+            },
+        }
+
+        self.events.push(SimEvent::PowerUp(ball.centre()));
+        self.power_up = None;
+    }
+
+    fn update_status_effects(&mut self, dt: f32) {
+        self.player1.update_effects(dt);
+        self.player2.update_effects(dt);
+    }
+
+    fn update_resize_effects(&mut self, dt: f32) {
+        for (paddle, resize_time) in [
+            (&mut self.player1, &mut self.player1_resize_time),
+            (&mut self.player2, &mut self.player2_resize_time),
+        ] {
+            if *resize_time > 0.0 {
+                *resize_time -= dt;
+
+                if *resize_time <= 0.0 {
+                    *resize_time = 0.0;
+                    paddle.set_height_scale(1.0);
+                }
+            }
+        }
+    }
+
+    fn bounce(&mut self, surface: Surface, ball: &Entity) {
+        self.events.push(SimEvent::Bounce {
+            surface,
+            position: ball.centre(),
+            speed: ball.velocity.magnitude(),
+        });
+    }
+
+    fn end_timed_match(&mut self) {
+        self.events.push(SimEvent::GameWon);
+
+        let scores = self.player_scores();
+        let best = scores.iter().map(|&(_, score)| score).max().unwrap_or(0);
+        let mut leaders = scores.iter().filter(|&&(_, score)| score == best);
+
+        let winner = match (leaders.next(), leaders.next()) {
+            (Some(&(edge, _)), None) => Some(edge),
+            _ => None,
+        };
+        self.events.push(SimEvent::MatchOver(winner));
+    }
+}
+
+pub struct GameState {
+    settings: Settings,
+    fullscreen: bool,
+    window_size: (i32, i32),
+    orientation: Orientation,
+    timer_text: Text,
+    player1_bricks_text: Text,
+    player2_bricks_text: Text,
+    theme: Theme,
+    // A full-screen quad whose corner colours are refreshed every frame.
+    background_mesh: Mesh,
+    background_time: f32,
+    stars: Vec<Star>,
+    // The effects' randomness, and each match's seed, so a seeded run is
+    // reproducible. The match draws from its own generator; see `Simulation`.
+    // Consumers, in call order within a frame:
+    // - `spawn_particles` on hits: two f32s per particle
+    // - `spawn_confetti` while the winner celebrates: three f32s per piece
+    // - `update_shake` while the court shakes: two f32s per update
+    // - `build_stars` in `new`: one usize and three f32s per star
+    // - `start_match`: one u64 per match
+    rng: StdRng,
+    sim: Simulation,
+    scene: Scene,
+    controls: Controls,
+    player3_score_text: Text,
+    player4_score_text: Text,
+    menu_idle_time: f32,
+    demo_text: Text,
+    // The match being recorded, saved to `REPLAY_PATH` when it ends.
+    recording: Option<Replay>,
+    playback: Option<Playback>,
+    replay_text: Text,
+    // Set for the length of a network match. The host also keeps the
+    // client's latest input and counts the states it has sent, and the
+    // client keeps the latest states it has been sent.
+    net: Option<NetLink>,
+    remote_input: RemoteInput,
+    net_tick: u32,
+    snapshots: SnapshotBuffer,
+    // Updates since the game started, for the event log.
+    frame: u64,
+    pub event_log: Option<EventLog>,
+    player1_score_text: Text,
+    player2_score_text: Text,
+    rally_text: Text,
+    status_text: Text,
+    player1_games_text: Text,
+    player2_games_text: Text,
+    winner: String,
+    winner_text: Text,
+    records: Records,
+    records_text: Text,
+    menu_index: usize,
+    title_text: Text,
+    menu_option_texts: Vec<Text>,
+    menu_text: Text,
+    options_index: usize,
+    options_title_text: Text,
+    option_texts: Vec<Text>,
+    options_hint_text: Text,
+    pixel: Texture,
+    player1_texture: Texture,
+    player2_texture: Texture,
+    player3_texture: Texture,
+    player4_texture: Texture,
+    ball_texture: Texture,
+    paddle_sound: PannedSound,
+    paddle_sound_cooldown: f32,
+    wall_sound: PannedSound,
+    score_sound: Sound,
+    point_text: Text,
+    intro_text: Text,
+    countdown_text: Text,
+    paused: bool,
+    // Escape asks before quitting. Everything stops while it's asking, and
+    // no replay frames are recorded.
+    confirm_quit: bool,
+    confirm_quit_text: Text,
+    particles: Vec<Particle>,
+    shake_time: f32,
+    shake_intensity: f32,
+    shake_offset: Vec2<f32>,
+    show_debug: bool,
+    // Set by F12 and handled in `draw`, where the frame can be read back.
+    screenshot_requested: bool,
+    // Messages waiting to be shown, with the time each has left on screen.
+    toasts: VecDeque<(String, f32)>,
+    toast_text: Text,
+    // Time since the match was won, driving the celebration on the game over
+    // screen; it runs up to `CELEBRATION_DURATION` and then holds still.
+    celebration_time: f32,
+    show_bounds: bool,
+    // F6 lays this match's heatmap over the court.
+    show_heatmap: bool,
+    // Debug builds only; see `WatchedTexture`.
+    watched_textures: Vec<WatchedTexture>,
+    texture_poll_time: f32,
+    frame_times: VecDeque<f32>,
+    debug_text: Text,
+    paused_text: Text,
+    music: Sound,
+    music_instance: Option<SoundInstance>,
+    music_muted: bool,
+    master_volume: f32,
+}
+
+impl GameState {
+    pub fn new(
+        ctx: &mut Context,
+        seed: Option<u64>,
+        settings: Settings,
+    ) -> tetra::Result<GameState> {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let player1_texture = load_texture(ctx, PLAYER1_TEXTURE_PATH, PLAYER1_PNG)?;
+        let player2_texture = load_texture(ctx, PLAYER2_TEXTURE_PATH, PLAYER2_PNG)?;
+
+        // The top and bottom paddles are the side paddles turned on their side.
+        let horizontal_size = Vec2::new(
+            player1_texture.height() as f32,
+            player1_texture.width() as f32,
+        );
+        let player3_texture = solid_texture(ctx, horizontal_size, [76, 209, 55, 255])?;
+        let player4_texture = solid_texture(ctx, horizontal_size, [251, 197, 49, 255])?;
+
+        let ball_texture = load_texture(ctx, BALL_TEXTURE_PATH, BALL_PNG)?;
+        let sim = Simulation::new(
+            settings,
+            Vec2::new(get_width(ctx) as f32, get_height(ctx) as f32),
+            [
+                texture_size(&player1_texture),
+                texture_size(&player2_texture),
+                horizontal_size,
+                horizontal_size,
+            ],
+            texture_size(&ball_texture),
+            StdRng::seed_from_u64(rng.gen()),
+        );
+
+        let score_font = load_font(ctx, "./fonts/wheaton.otf", 64.0)?;
+        let message_font = load_font(ctx, "./fonts/wheaton.otf", 32.0)?;
+
+        let pixel = Texture::from_data(ctx, 1, 1, TextureFormat::Rgba8, &[255, 255, 255, 255])?;
+        let background_vertices = [Vertex::new(Vec2::zero(), Vec2::zero(), Color::WHITE); 4];
+        let background_mesh = Mesh::indexed(
+            VertexBuffer::with_usage(ctx, &background_vertices, BufferUsage::Dynamic)?,
+            IndexBuffer::new(ctx, &[0, 1, 2, 2, 3, 0])?,
+        );
+
+        Ok(GameState {
+            settings,
+            fullscreen: settings.fullscreen,
+            window_size: window::get_size(ctx),
+            orientation: Orientation::Horizontal,
+            timer_text: Text::new(timer_message(settings.match_duration), message_font.clone()),
+            player1_bricks_text: Text::new(bricks_message(0), message_font.clone()),
+            player2_bricks_text: Text::new(bricks_message(0), message_font.clone()),
+            theme: active_theme(&settings),
+            background_mesh,
+            background_time: 0.0,
+            stars: build_stars(&mut rng, get_width(ctx) as f32, get_height(ctx) as f32),
+            rng,
+            sim,
+            scene: Scene::Menu,
+            controls: Controls::default(),
+            player3_score_text: Text::new("0", score_font.clone()),
+            player4_score_text: Text::new("0", score_font.clone()),
+            menu_idle_time: 0.0,
+            demo_text: Text::new("DEMO - press any key", message_font.clone()),
+            recording: None,
+            playback: None,
+            net: None,
+            remote_input: RemoteInput::default(),
+            net_tick: 0,
+            snapshots: SnapshotBuffer::default(),
+            frame: 0,
+            event_log: None,
+            replay_text: Text::new("REPLAY - press any key", message_font.clone()),
+            player1_score_text: Text::new("0", score_font.clone()),
+            player2_score_text: Text::new("0", score_font.clone()),
+            rally_text: Text::new(rally_message(0, 0), message_font.clone()),
+            status_text: Text::new("", message_font.clone()),
+            player1_games_text: Text::new(games_message(0), message_font.clone()),
+            player2_games_text: Text::new(games_message(0), message_font.clone()),
+            winner: String::new(),
+            winner_text: Text::new("", message_font.clone()),
+            records: Records::load(),
+            records_text: Text::new("", message_font.clone()),
+            menu_index: 0,
+            title_text: Text::new("PONG", score_font.clone()),
+            menu_option_texts: MENU_OPTIONS
+                .iter()
+                .map(|option| Text::new(option.label(), message_font.clone()))
+                .collect(),
+            options_index: 0,
+            options_title_text: Text::new("OPTIONS", score_font.clone()),
+            option_texts: OPTION_ITEMS
+                .iter()
+                .map(|&item| {
+                    Text::new(
+                        option_label(item, &settings, Difficulty::Medium, settings.master_volume),
+                        message_font.clone(),
+                    )
+                })
+                .collect(),
+            options_hint_text: Text::new(OPTIONS_HINT, message_font.clone()),
+            menu_text: Text::new(
+                menu_message(
+                    Difficulty::Medium,
+                    Court::Open,
+                    MatchMode::Points,
+                    Orientation::Horizontal,
+                ),
+                message_font.clone(),
+            ),
+            pixel,
+            player1_texture,
+            player2_texture,
+            player3_texture,
+            player4_texture,
+            ball_texture,
+            paddle_sound: load_panned_sound("paddle", PADDLE_LEFT_WAV, PADDLE_RIGHT_WAV),
+            paddle_sound_cooldown: 0.0,
+            wall_sound: load_panned_sound("wall", WALL_LEFT_WAV, WALL_RIGHT_WAV),
+            score_sound: load_sound("./sfx/score.wav", SCORE_WAV),
+            point_text: Text::new("POINT!", score_font.clone()),
+            intro_text: Text::new("GET READY", message_font.clone()),
+            countdown_text: Text::new("", score_font.clone()),
+            paused: false,
+            confirm_quit: false,
+            confirm_quit_text: Text::new("Quit? Y/N", score_font.clone()),
+            particles: Vec::new(),
+            shake_time: 0.0,
+            shake_intensity: 0.0,
+            shake_offset: Vec2::zero(),
+            show_debug: false,
+            screenshot_requested: false,
+            toasts: VecDeque::new(),
+            toast_text: Text::new("", message_font.clone()),
+            celebration_time: CELEBRATION_DURATION,
+            show_bounds: false,
+            show_heatmap: false,
+            watched_textures: watched_textures(),
+            texture_poll_time: 0.0,
+            frame_times: VecDeque::with_capacity(FRAME_TIME_SAMPLES),
+            debug_text: Text::new("", message_font.clone()),
+            paused_text: Text::new("PAUSED", score_font.clone()),
+            music: load_sound("./sfx/music.wav", MUSIC_WAV),
+            music_instance: None,
+            music_muted: false,
+            master_volume: settings.master_volume,
+        })
+    }
+
+    fn start_match(&mut self, ctx: &Context) {
+        let seed = self.rng.gen();
+        self.start_match_seeded(ctx, seed);
+
+        if self.counts_for_records() {
+            self.recording = Some(Replay {
+                seed,
+                four_player: self.sim.four_player,
+                player2_control: self.sim.player2_control,
+                extra_control: self.sim.extra_control,
+                difficulty: self.sim.difficulty,
+                court: self.sim.court,
+                match_mode: self.sim.match_mode,
+                orientation: self.orientation,
+                gravity: self.settings.gravity,
+                catch_assist: self.settings.catch_assist,
+                settings: ReplaySettings::of(&self.settings),
+                frames: Vec::new(),
+            });
+        }
+    }
+
+    fn start_match_seeded(&mut self, ctx: &Context, seed: u64) {
+        self.sim.apply_settings(self.settings);
+        self.fit_to_window(ctx);
+        self.sim.start(seed);
+        self.sim.last_mouse_y = mouse_along_paddles(ctx, self.orientation);
+        self.scene = Scene::Playing;
+        self.winner = String::new();
+        self.timer_text
+            .set_content(timer_message(self.sim.match_timer));
+        self.update_score_texts();
+        self.paused = false;
+        self.particles.clear();
+        self.shake_time = 0.0;
+        self.shake_offset = Vec2::zero();
+        self.start_music(ctx);
+    }
+
+    fn start_demo(&mut self, ctx: &Context) {
+        self.sim.demo = true;
+        self.sim.four_player = false;
+        self.set_orientation(Orientation::Horizontal);
+        self.sim.player2_control = ControlMode::Ai;
+        self.start_match(ctx);
+    }
+
+    // Replays the last recorded match with the modes it was played in.
+    fn start_playback(&mut self, ctx: &Context) {
+        let replay = match Replay::load(REPLAY_PATH) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Could not load replay from {}: {}", REPLAY_PATH, e);
+                return;
+            }
+        };
+
+        self.sim.four_player = replay.four_player;
+        self.sim.player2_control = replay.player2_control;
+        self.sim.extra_control = replay.extra_control;
+        self.sim.difficulty = replay.difficulty;
+        self.sim.court = replay.court;
+        self.sim.match_mode = replay.match_mode;
+        self.set_orientation(replay.orientation);
+
+        let seed = replay.seed;
+        let playback = Playback::new(replay, self.settings);
+        self.settings = playback.settings();
+        self.playback = Some(playback);
+        self.start_match_seeded(ctx, seed);
+    }
+
+    fn stop_playback(&mut self) {
+        if let Some(playback) = self.playback.take() {
+            self.settings = playback.live_settings;
+            self.sim.apply_settings(self.settings);
+        }
+    }
+
+    // Leaves the attract demo or a replay.
+    fn return_to_menu(&mut self) {
+        self.sim.demo = false;
+        self.stop_playback();
+        self.recording = None;
+        self.net = None;
+        self.menu_idle_time = 0.0;
+        self.scene = Scene::Menu;
+    }
+
+    // Both ends play a plain two-player match, though only the host's copy is
+    // simulated. Network matches aren't recorded.
+    pub fn start_network_match(&mut self, ctx: &Context, link: NetLink) {
+        self.sim.four_player = false;
+        self.sim.player2_control = ControlMode::Human;
+        self.sim.court = Court::Open;
+        self.set_orientation(Orientation::Horizontal);
+        self.net = Some(link);
+        self.remote_input = RemoteInput::default();
+        self.net_tick = 0;
+        self.snapshots = SnapshotBuffer::default();
+        let seed = self.rng.gen();
+        self.start_match_seeded(ctx, seed);
+    }
+
+    fn net_role(&self) -> Option<NetRole> {
+        self.net.as_ref().map(|link| link.role)
+    }
+
+    fn connection_lost(&mut self, reason: &str) {
+        eprintln!("Connection lost: {}", reason);
+        self.return_to_menu();
+        self.push_toast("Connection lost");
+    }
+
+    // Collects the client's input for the next tick. A dash press is held
+    // until a tick uses it, in case two inputs arrive together.
+    fn receive_remote_input(&mut self) {
+        let Some(link) = &mut self.net else {
+            return;
+        };
+
+        match link.receive() {
+            Ok(messages) => {
+                for message in messages {
+                    if let NetMessage::Input(input) = message {
+                        let dash = self.remote_input.dash || input.dash;
+                        self.remote_input = RemoteInput { dash, ..input };
+                    }
+                }
+            }
+            Err(e) => self.connection_lost(&e),
+        }
+    }
+
+    fn send_net_state(&mut self) {
+        let winner = match (self.scene, self.winner.as_str()) {
+            (Scene::GameOver, "Player 1") => 1,
+            (Scene::GameOver, "Player 2") => 2,
+            (Scene::GameOver, _) => 3,
+            _ => 0,
+        };
+        let state = NetState {
+            tick: self.net_tick,
+            player1: self.sim.player1.position,
+            player2: self.sim.player2.position,
+            player1_height_scale: self.sim.player1.height_scale,
+            player2_height_scale: self.sim.player2.height_scale,
+            point_flash: self.sim.point_flash,
+            serve_countdown: self.sim.serve_countdown,
+            intro_countdown: self.sim.intro_countdown,
+            balls: self.sim.balls.iter().map(|ball| ball.position).collect(),
+            player1_score: self.sim.player1_score,
+            player2_score: self.sim.player2_score,
+            winner,
+        };
+        self.net_tick = self.net_tick.wrapping_add(1);
+
+        let Some(link) = &mut self.net else {
+            return;
+        };
+
+        if let Err(e) = link.broadcast(&NetMessage::State(state)) {
+            self.connection_lost(&e);
+        }
+    }
+
+    // The client sends its input, predicts its own paddle from it and draws
+    // the host's state. Either player's keys steer its paddle. A spectator
+    // only draws.
+    fn update_client(&mut self, ctx: &mut Context) {
+        let input = FrameInput::read(ctx, &self.controls, self.orientation, self.sim.last_mouse_y);
+        let remote = RemoteInput {
+            direction: (input.player1 + input.player2).clamp(-1.0, 1.0),
+            serve: input.serve1 || input.serve2,
+            dash: input.dash[0] || input.dash[1],
+        };
+
+        let Some(link) = &mut self.net else {
+            return;
+        };
+        let playing = link.role == NetRole::Client;
+        let sent = if playing {
+            link.send(&NetMessage::Input(remote))
+        } else {
+            Ok(())
+        };
+
+        match sent.and_then(|()| link.receive()) {
+            Ok(messages) => {
+                self.sim.settle_entities();
+                self.snapshots.advance();
+
+                if playing && self.scene == Scene::Playing {
+                    let dt = time::get_delta_time(ctx).as_secs_f32();
+                    let height = self.court_size(ctx).y;
+                    self.sim
+                        .player2
+                        .steer(remote.direction, remote.dash, false, dt);
+                    self.sim
+                        .player2
+                        .clamp_vertical(0.0, height - self.sim.player2.height());
+                }
+
+                for message in messages {
+                    if let NetMessage::State(state) = message {
+                        self.apply_net_state(state);
+                    }
+                }
+
+                if self.scene == Scene::GameOver {
+                    let dt = time::get_delta_time(ctx).as_secs_f32();
+                    self.update_celebration(ctx, dt);
+                }
+            }
+            Err(e) => self.connection_lost(&e),
+        }
+    }
+
+    // Positions are left to `show_snapshot`, apart from reconciling the
+    // client's predicted paddle.
+    fn apply_net_state(&mut self, state: NetState) {
+        if self.net_role() == Some(NetRole::Client) {
+            let moving = self.sim.player2.velocity.y != 0.0;
+            self.sim.player2.position.y =
+                reconcile(self.sim.player2.position.y, state.player2.y, moving);
+        }
+        self.sim.player1.height_scale = state.player1_height_scale;
+        self.sim.player2.height_scale = state.player2_height_scale;
+        self.sim.point_flash = state.point_flash;
+        self.sim.serve_countdown = state.serve_countdown;
+        self.sim.intro_countdown = state.intro_countdown;
+
+        if (self.sim.player1_score, self.sim.player2_score)
+            != (state.player1_score, state.player2_score)
+        {
+            self.sim.player1_score = state.player1_score;
+            self.sim.player2_score = state.player2_score;
+            self.update_score_texts();
+        }
+
+        // The host starts the rematch, and the client follows it back in.
+        match (self.scene, state.winner) {
+            (Scene::Playing, 1) => self.set_winner("Player 1"),
+            (Scene::Playing, 2) => self.set_winner("Player 2"),
+            (Scene::Playing, 3) => self.end_match("Draw", "Draw!"),
+            (Scene::GameOver, 0) => {
+                self.scene = Scene::Playing;
+                self.snapshots.clear();
+            }
+            _ => {}
+        }
+
+        self.snapshots.push(state);
+    }
+
+    // Places the remote paddles and the balls between the two newest
+    // snapshots for this frame. A spectator has no paddle of its own.
+    fn show_snapshot(&mut self, ctx: &Context) {
+        let Some(state) = self.snapshots.sample(time::get_blend_factor(ctx)) else {
+            return;
+        };
+
+        self.sim.player1.position = state.player1;
+        self.sim.player1.settle();
+        if self.net_role() == Some(NetRole::Spectator) {
+            self.sim.player2.position = state.player2;
+            self.sim.player2.settle();
+        }
+
+        // Every ball is the same size, so extra balls take the first's.
+        let size = self.sim.balls[0].base_size;
+        self.sim.balls.truncate(state.balls.len().max(1));
+        for (i, &position) in state.balls.iter().enumerate() {
+            match self.sim.balls.get_mut(i) {
+                Some(ball) => ball.position = position,
+                None => self.sim.balls.push(Entity::new(size, position)),
+            }
+        }
+
+        for ball in &mut self.sim.balls {
+            ball.settle();
+        }
+    }
+
+    fn log_event(&mut self, event: BallEvent, position: Vec2<f32>, speed: f32) {
+        let Some(log) = &mut self.event_log else {
+            return;
+        };
+
+        if let Err(e) = log.record(&event_row(self.frame, event, position, speed)) {
+            eprintln!("Stopped logging events: {}", e);
+            self.event_log = None;
+        }
+    }
+
+    // Demos and replays are only watched, so they don't count towards records.
+    fn counts_for_records(&self) -> bool {
+        !self.sim.demo && self.playback.is_none()
+    }
+
+    fn snapshot(&self) -> SaveData {
+        SaveData {
+            scene: self.scene,
+            winner: self.winner.clone(),
+            four_player: self.sim.four_player,
+            player2_control: self.sim.player2_control,
+            extra_control: self.sim.extra_control,
+            difficulty: self.sim.difficulty,
+            court: self.sim.court,
+            match_mode: self.sim.match_mode,
+            orientation: self.orientation,
+            player1_score: self.sim.player1_score,
+            player2_score: self.sim.player2_score,
+            player3_score: self.sim.player3_score,
+            player4_score: self.sim.player4_score,
+            games_won_p1: self.sim.games_won_p1,
+            games_won_p2: self.sim.games_won_p2,
+            rally_hits: self.sim.rally_hits,
+            longest_rally: self.sim.longest_rally,
+            player1: self.sim.player1.state(),
+            player2: self.sim.player2.state(),
+            player3: self.sim.player3.state(),
+            player4: self.sim.player4.state(),
+            balls: self.sim.balls.iter().map(Entity::state).collect(),
+            point_flash: self.sim.point_flash,
+            point_message: self.point_text.content().to_string(),
+            serve_countdown: self.sim.serve_countdown,
+            intro_countdown: self.sim.intro_countdown,
+            match_timer: self.sim.match_timer,
+            time_scale: self.sim.time_scale,
+            power_up: self.sim.power_up,
+            power_up_timer: self.sim.power_up_timer,
+            last_hitter: self.sim.last_hitter,
+            last_touch: self.sim.last_touch,
+            player1_resize_time: self.sim.player1_resize_time,
+            player2_resize_time: self.sim.player2_resize_time,
+            player1_shield_active: self.sim.player1_shield_active,
+            player2_shield_active: self.sim.player2_shield_active,
+            obstacle: self.sim.obstacle,
+            obstacle_velocity: self.sim.obstacle_velocity,
+            bricks: self.sim.bricks.clone(),
+            bricks_p1: self.sim.bricks_p1,
+            bricks_p2: self.sim.bricks_p2,
+            stats: self.sim.stats,
+        }
+    }
+
+    fn restore(&mut self, ctx: &Context, data: SaveData) {
+        // Every ball is the same size, so extra balls take the first's.
+        let size = self.sim.balls[0].base_size;
+        self.sim.balls = data
+            .balls
+            .iter()
+            .map(|state| {
+                let mut ball = Entity::new(size, state.position);
+                ball.restore(state);
+                ball
+            })
+            .collect();
+
+        if self.sim.balls.is_empty() {
+            self.sim.balls.push(Entity::new(size, Vec2::zero()));
+            self.sim.reset_ball();
+        }
+
+        self.sim.player1.restore(&data.player1);
+        self.sim.player2.restore(&data.player2);
+        self.sim.player3.restore(&data.player3);
+        self.sim.player4.restore(&data.player4);
+
+        self.sim.four_player = data.four_player;
+        self.sim.player2_control = data.player2_control;
+        self.sim.extra_control = data.extra_control;
+        self.sim.difficulty = data.difficulty;
+        self.sim.court = data.court;
+        self.sim.match_mode = data.match_mode;
+        self.set_orientation(data.orientation);
+        self.sim.player1_score = data.player1_score;
+        self.sim.player2_score = data.player2_score;
+        self.sim.player3_score = data.player3_score;
+        self.sim.player4_score = data.player4_score;
+        self.sim.games_won_p1 = data.games_won_p1;
+        self.sim.games_won_p2 = data.games_won_p2;
+        self.sim.rally_hits = data.rally_hits;
+        self.sim.longest_rally = data.longest_rally;
+        self.sim.point_flash = data.point_flash;
+        self.point_text.set_content(data.point_message);
+        self.sim.serve_countdown = data.serve_countdown;
+        self.sim.intro_countdown = data.intro_countdown;
+        self.sim.match_timer = data.match_timer;
+        self.sim.time_scale = data.time_scale;
+        self.sim.power_up = data.power_up;
+        self.sim.power_up_timer = data.power_up_timer;
+        self.sim.last_hitter = data.last_hitter;
+        self.sim.last_touch = data.last_touch;
+        self.sim.player1_resize_time = data.player1_resize_time;
+        self.sim.player2_resize_time = data.player2_resize_time;
+        self.sim.player1_shield_active = data.player1_shield_active;
+        self.sim.player2_shield_active = data.player2_shield_active;
+        self.sim.obstacle = data.obstacle;
+        self.sim.obstacle_velocity = data.obstacle_velocity;
+        self.sim.bricks = data.bricks;
+        self.sim.bricks_p1 = data.bricks_p1;
+        self.sim.bricks_p2 = data.bricks_p2;
+        self.sim.stats = data.stats;
+
+        self.particles.clear();
+        self.sim.ball_trail.clear();
+        self.shake_time = 0.0;
+        self.shake_offset = Vec2::zero();
+        self.sim.hitstop_frames = 0;
+        self.sim.player1_ai.reset();
+        self.sim.player2_ai.reset();
+        self.sim.player3_ai.reset();
+        self.sim.player4_ai.reset();
+        self.sim.serve_charge = 0.0;
+        self.paused = false;
+        self.sim.demo = false;
+        self.recording = None;
+        self.stop_playback();
+        self.sim.apply_settings(self.settings);
+        self.sim.last_mouse_y = mouse_along_paddles(ctx, self.orientation);
+
+        self.update_score_texts();
+        self.update_menu_text();
+        self.timer_text
+            .set_content(timer_message(self.sim.match_timer));
+
+        match data.scene {
+            Scene::GameOver if data.winner == "Draw" => self.end_match("Draw", "Draw!"),
+            Scene::GameOver => self.set_winner(&data.winner),
+            _ => {
+                self.scene = Scene::Playing;
+                self.winner = data.winner;
+            }
+        }
+
+        self.fit_to_window(ctx);
+        self.start_music(ctx);
+    }
+
+    fn save_game(&self) {
+        let result = serde_json::to_string(&self.snapshot())
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(SAVE_PATH, json).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(()) => eprintln!("Saved game to {}", SAVE_PATH),
+            Err(e) => eprintln!("Could not save game to {}: {}", SAVE_PATH, e),
+        }
+    }
+
+    fn load_game(&mut self, ctx: &Context) {
+        let result = std::fs::read_to_string(SAVE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<SaveData>(&json).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(data) => self.restore(ctx, data),
+            Err(e) => eprintln!("Could not load game from {}: {}", SAVE_PATH, e),
+        }
+    }
+
+    fn start_music(&mut self, ctx: &Context) {
+        if self.music_instance.is_some() {
+            return;
+        }
+
+        match self.music.spawn(ctx) {
+            Ok(instance) => {
+                instance.set_repeating(true);
+                instance.set_volume(self.music_volume());
+                instance.play();
+                self.music_instance = Some(instance);
+            }
+            Err(e) => eprintln!("Could not play music: {}", e),
+        }
+    }
+
+    fn music_volume(&self) -> f32 {
+        if self.music_muted || !self.settings.sound {
+            0.0
+        } else {
+            MUSIC_VOLUME * self.master_volume
+        }
+    }
+
+    // At zero volume nothing is played at all.
+    fn effects_volume(&self) -> f32 {
+        if self.settings.sound {
+            self.master_volume
+        } else {
+            0.0
+        }
+    }
+
+    fn toggle_music_mute(&mut self) {
+        self.music_muted = !self.music_muted;
+        self.apply_music_volume();
+        self.push_toast(if self.music_muted {
+            "Music muted"
+        } else {
+            "Music unmuted"
+        });
+    }
+
+    fn apply_music_volume(&self) {
+        if let Some(instance) = &self.music_instance {
+            instance.set_volume(self.music_volume());
+        }
+    }
+
+    fn play_sound(&self, ctx: &Context, sound: &Sound) {
+        let volume = self.effects_volume();
+
+        if volume <= 0.0 {
+            return;
+        }
+
+        if let Err(e) = sound.play_with(ctx, volume, 1.0) {
+            eprintln!("Could not play sound: {}", e);
+        }
+    }
+
+    fn play_panned(&self, ctx: &Context, sound: &PannedSound, pan: f32) {
+        let master_volume = self.effects_volume();
+
+        if master_volume <= 0.0 {
+            return;
+        }
+
+        let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+
+        for (side, volume) in [(&sound.left, angle.cos()), (&sound.right, angle.sin())] {
+            if let Err(e) = side.play_with(ctx, volume * master_volume, 1.0) {
+                eprintln!("Could not play sound: {}", e);
+            }
+        }
+    }
+
+    fn court_size(&self, ctx: &Context) -> Vec2<f32> {
+        let screen = Vec2::new(get_width(ctx) as f32, get_height(ctx) as f32);
+        court_size(screen, self.orientation)
+    }
+
+    // Shake applies on top of the turn, so the court shakes along the screen.
+    fn court_transform(&self, ctx: &Context) -> Mat4<f32> {
+        let screen = Vec2::new(get_width(ctx) as f32, get_height(ctx) as f32);
+        Mat4::<f32>::translation_2d(self.shake_offset) * court_transform(screen, self.orientation)
+    }
+
+    // Pans by where a sound comes from across the screen, whichever way it's
+    // turned.
+    fn pan(&self, ctx: &Context, position: Vec2<f32>) -> f32 {
+        let court = self.court_size(ctx);
+        let across = match self.orientation {
+            Orientation::Horizontal => position.x / court.x,
+            Orientation::Vertical => position.y / court.y,
+        };
+
+        (across * 2.0 - 1.0).clamp(-1.0, 1.0)
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+        self.controls = Controls::for_orientation(orientation);
+        self.update_menu_text();
+    }
+
+    // Keeps the court in step with the drawable size.
+    fn fit_to_window(&mut self, ctx: &Context) {
+        self.sim.fit(self.court_size(ctx));
+    }
+
+    fn set_winner(&mut self, winner: &str) {
+        self.end_match(winner, &format!("{} wins!", winner));
+    }
+
+    fn end_match(&mut self, winner: &str, headline: &str) {
+        if let Some(recording) = self.recording.take() {
+            recording.save(REPLAY_PATH);
+        }
+
+        self.scene = Scene::GameOver;
+        self.winner = winner.to_string();
+        // A draw has nobody to celebrate.
+        self.celebration_time = if winner_index(winner).is_some() {
+            0.0
+        } else {
+            CELEBRATION_DURATION
+        };
+        self.winner_text.set_content(game_over_message(
+            headline,
+            &self.sim.stats,
+            self.sim.longest_rally,
+        ));
+    }
+
+    fn update_score_texts(&mut self) {
+        self.player1_score_text
+            .set_content(self.sim.player1_score.to_string());
+        self.player2_score_text
+            .set_content(self.sim.player2_score.to_string());
+        self.player3_score_text
+            .set_content(self.sim.player3_score.to_string());
+        self.player4_score_text
+            .set_content(self.sim.player4_score.to_string());
+        self.rally_text
+            .set_content(rally_message(self.sim.rally_hits, self.sim.longest_rally));
+        self.player1_games_text
+            .set_content(games_message(self.sim.games_won_p1));
+        self.player2_games_text
+            .set_content(games_message(self.sim.games_won_p2));
+        self.player1_bricks_text
+            .set_content(bricks_message(self.sim.bricks_p1));
+        self.player2_bricks_text
+            .set_content(bricks_message(self.sim.bricks_p2));
+        self.status_text.set_content(
+            game_status(
+                self.sim.player1_score,
+                self.sim.player2_score,
+                self.settings.win_score,
+            )
+            .label(),
+        );
+    }
+
+    fn draw_scores(&mut self, ctx: &mut Context) {
+        let width = get_width(ctx) as f32;
+        let height = get_height(ctx) as f32;
+        let text_color = self.theme.text;
+
+        let mut texts = vec![
+            (&mut self.player1_score_text, width / 4.0, 16.0),
+            (&mut self.player2_score_text, width * 3.0 / 4.0, 16.0),
+            (&mut self.player1_games_text, width / 4.0, 96.0),
+            (&mut self.player2_games_text, width * 3.0 / 4.0, 96.0),
+            (&mut self.rally_text, width / 2.0, 24.0),
+        ];
+
+        // Four-player games are a race to the winning score, with no deuce.
+        match self.sim.match_mode {
+            _ if self.sim.player2_control == ControlMode::Wall => {}
+            MatchMode::Timed => texts.push((&mut self.timer_text, width / 2.0, 64.0)),
+            MatchMode::Points if !self.sim.four_player => {
+                texts.push((&mut self.status_text, width / 2.0, 64.0))
+            }
+            MatchMode::Points => {}
+        }
+
+        if self.sim.four_player {
+            texts.push((&mut self.player3_score_text, width / 2.0, 104.0));
+            texts.push((&mut self.player4_score_text, width / 2.0, height - 200.0));
+        }
+
+        if self.sim.court == Court::Bricks {
+            texts.push((&mut self.player1_bricks_text, width / 4.0, 136.0));
+            texts.push((&mut self.player2_bricks_text, width * 3.0 / 4.0, 136.0));
+        }
+
+        for (text, x, y) in texts {
+            let text_width = text.get_bounds(ctx).map_or(0.0, |bounds| bounds.width);
+            text.draw(
+                ctx,
+                DrawParams::new()
+                    .position(Vec2::new(x - text_width / 2.0, y))
+                    .color(text_color),
+            );
+        }
+    }
+
+    fn update_menu_text(&mut self) {
+        self.menu_text.set_content(menu_message(
+            self.sim.difficulty,
+            self.sim.court,
+            self.sim.match_mode,
+            self.orientation,
+        ));
+    }
+
+    fn update_menu(&mut self, ctx: &mut Context) {
+        self.menu_idle_time += time::get_delta_time(ctx).as_secs_f32();
+
+        if is_any_input_pressed(ctx) {
+            self.menu_idle_time = 0.0;
+        }
+
+        if self.menu_idle_time >= ATTRACT_IDLE_TIME {
+            self.start_demo(ctx);
+            return;
+        }
+
+        if input::is_key_pressed(ctx, Key::D) {
+            self.sim.difficulty = self.sim.difficulty.next();
+            self.update_menu_text();
+        }
+
+        if input::is_key_pressed(ctx, Key::O) {
+            self.sim.court = self.sim.court.next();
+            self.update_menu_text();
+        }
+
+        if input::is_key_pressed(ctx, Key::G) {
+            self.sim.match_mode = self.sim.match_mode.next();
+            self.update_menu_text();
+        }
+
+        if input::is_key_pressed(ctx, Key::V) {
+            self.set_orientation(self.orientation.next());
+        }
+
+        if input::is_key_pressed(ctx, Key::R) {
+            self.start_playback(ctx);
+            return;
+        }
+
+        if input::is_key_pressed(ctx, Key::Up) {
+            self.menu_index = (self.menu_index + MENU_OPTIONS.len() - 1) % MENU_OPTIONS.len();
+        }
+
+        if input::is_key_pressed(ctx, Key::Down) {
+            self.menu_index = (self.menu_index + 1) % MENU_OPTIONS.len();
+        }
+
+        if is_confirm_pressed(ctx) {
+            match MENU_OPTIONS[self.menu_index] {
+                MenuOption::OnePlayer => {
+                    self.sim.four_player = false;
+                    self.sim.player2_control = ControlMode::Ai;
+                    self.start_match(ctx);
+                }
+                MenuOption::TwoPlayer => {
+                    self.sim.four_player = false;
+                    self.sim.player2_control = ControlMode::Human;
+                    self.start_match(ctx);
+                }
+                MenuOption::FourPlayer => {
+                    self.sim.four_player = true;
+                    self.sim.player2_control = ControlMode::Human;
+                    self.start_match(ctx);
+                }
+                MenuOption::Practice => {
+                    self.sim.four_player = false;
+                    self.sim.player2_control = ControlMode::Wall;
+                    self.start_match(ctx);
+                }
+                MenuOption::Options => {
+                    self.options_index = 0;
+                    self.update_options_text();
+                    self.scene = Scene::Options;
+                }
+                MenuOption::Quit => {
+                    self.records.save();
+                    window::quit(ctx);
+                }
+            }
+        }
+    }
+
+    fn update_options_text(&mut self) {
+        for (text, &item) in self.option_texts.iter_mut().zip(OPTION_ITEMS.iter()) {
+            text.set_content(option_label(
+                item,
+                &self.settings,
+                self.sim.difficulty,
+                self.master_volume,
+            ));
+        }
+    }
+
+    // Changes apply straight away; leaving writes them back to the config file.
+    fn update_options(&mut self, ctx: &mut Context) {
+        if input::is_key_pressed(ctx, Key::Up) {
+            self.options_index = (self.options_index + OPTION_ITEMS.len() - 1) % OPTION_ITEMS.len();
+        }
+
+        if input::is_key_pressed(ctx, Key::Down) {
+            self.options_index = (self.options_index + 1) % OPTION_ITEMS.len();
+        }
+
+        let item = OPTION_ITEMS[self.options_index];
+        let step = if input::is_key_pressed(ctx, Key::Left) {
+            -1
+        } else if input::is_key_pressed(ctx, Key::Right)
+            || (is_confirm_pressed(ctx)
+                && matches!(
+                    item,
+                    OptionItem::Sound | OptionItem::Gravity | OptionItem::CatchAssist
+                ))
+        {
+            1
+        } else {
+            0
+        };
+
+        if step != 0 {
+            match item {
+                OptionItem::Difficulty if step < 0 => {
+                    self.sim.difficulty = self.sim.difficulty.previous()
+                }
+                OptionItem::Difficulty => self.sim.difficulty = self.sim.difficulty.next(),
+                OptionItem::Volume => self.master_volume = step_volume(self.master_volume, step),
+                _ => adjust_setting(&mut self.settings, item, step),
+            }
+
+            self.apply_music_volume();
+            self.update_options_text();
+            self.update_menu_text();
+        }
+
+        if item == OptionItem::Back && is_confirm_pressed(ctx) {
+            self.save_options();
+            self.scene = Scene::Menu;
+        }
+    }
+
+    // Only the values on the options screen are written, over what's in the
+    // file, so command-line overrides aren't saved along with them. Unlike
+    // `Settings::load`, a file that can't be read or parsed isn't treated as
+    // the defaults, since saving over it would lose the user's other settings.
+    fn save_options(&self) {
+        let saved = match std::fs::read_to_string(SETTINGS_PATH) {
+            Ok(contents) => Settings::parse(&contents).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(e.to_string()),
+        };
+        let mut saved = match saved.and_then(|saved| saved.validate().map(|()| saved)) {
+            Ok(saved) => saved,
+            Err(e) => {
+                eprintln!("Not saving options over invalid {}: {}", SETTINGS_PATH, e);
+                return;
+            }
+        };
+
+        saved.win_score = self.settings.win_score;
+        saved.ball_speed = self.settings.ball_speed;
+        saved.sound = self.settings.sound;
+        saved.master_volume = self.master_volume;
+        saved.gravity = self.settings.gravity;
+        saved.catch_assist = self.settings.catch_assist;
+        saved.save(SETTINGS_PATH);
+    }
+
+    fn update_playing(&mut self, ctx: &mut Context) {
+        self.sim.settle_entities();
+
+        let mut input = match &mut self.playback {
+            Some(playback) => match playback.next_input() {
+                Some(input) => input,
+                None => {
+                    self.return_to_menu();
+                    return;
+                }
+            },
+            None => FrameInput::read(ctx, &self.controls, self.orientation, self.sim.last_mouse_y),
+        };
+
+        if self.net.is_some() {
+            self.remote_input.apply(&mut input);
+            self.remote_input.dash = false;
+        }
+
+        if let Some(recording) = &mut self.recording {
+            recording.frames.push(input);
+        }
+
+        if input.pause {
+            self.paused = !self.paused;
+        }
+
+        if self.paused {
+            return;
+        }
+
+        let dt = time::get_delta_time(ctx).as_secs_f32();
+
+        // The effects hold still through a hitstop along with the match.
+        if self.sim.hitstop_frames == 0 {
+            self.paddle_sound_cooldown = (self.paddle_sound_cooldown - dt).max(0.0);
+            update_particles(&mut self.particles, dt);
+            self.update_shake(dt);
+        }
+
+        let events = self.sim.step(&input, dt);
+
+        if input.next_court {
+            self.update_menu_text();
+        }
+
+        if self.sim.match_mode == MatchMode::Timed {
+            let remaining = timer_message(self.sim.match_timer);
+            if self.timer_text.content() != remaining {
+                self.timer_text.set_content(remaining);
+            }
+        }
+
+        for event in events {
+            self.show_event(ctx, event);
+        }
+    }
+
+    fn show_event(&mut self, ctx: &mut Context, event: SimEvent) {
+        match event {
+            SimEvent::PaddleHit {
+                edge,
+                position,
+                speed,
+                rally,
+            } => {
+                rumble(ctx, edge.gamepad_id(), speed);
+                spawn_particles(&mut self.particles, &mut self.rng, position);
+
+                // The top and bottom paddles don't set records.
+                if matches!(edge, Edge::Left | Edge::Right) && self.counts_for_records() {
+                    self.records.longest_rally = self.records.longest_rally.max(rally);
+                    self.records.fastest_ball = self.records.fastest_ball.max(speed);
+                }
+                self.rally_text
+                    .set_content(rally_message(self.sim.rally_hits, self.sim.longest_rally));
+
+                self.log_event(BallEvent::Paddle, position, speed);
+
+                if self.paddle_sound_cooldown <= 0.0 {
+                    self.play_panned(ctx, &self.paddle_sound, self.pan(ctx, position));
+                    self.paddle_sound_cooldown = SOUND_MIN_INTERVAL;
+                }
+            }
+            SimEvent::Bounce {
+                surface,
+                position,
+                speed,
+            } => {
+                match surface {
+                    Surface::Wall => self.log_event(BallEvent::Wall, position, speed),
+                    Surface::Brick => self.update_score_texts(),
+                    Surface::Obstacle | Surface::Shield => {}
+                }
+                self.play_panned(ctx, &self.wall_sound, self.pan(ctx, position));
+                spawn_particles(&mut self.particles, &mut self.rng, position);
+            }
+            SimEvent::PowerUp(position) => {
+                spawn_particles(&mut self.particles, &mut self.rng, position);
+            }
+            SimEvent::Out { position, speed } => {
+                self.log_event(BallEvent::Score, position, speed);
+                self.play_sound(ctx, &self.score_sound);
+                self.shake_time = SHAKE_DURATION;
+                self.shake_intensity = SHAKE_INTENSITY;
+            }
+            SimEvent::Point(message) => {
+                self.point_text.set_content(message);
+                self.update_score_texts();
+            }
+            SimEvent::GameWon => {
+                if self.counts_for_records() {
+                    self.records.games_played += 1;
+                    self.records.save();
+                }
+            }
+            SimEvent::MatchOver(winner) => {
+                self.update_score_texts();
+                match winner {
+                    Some(edge) => self.set_winner(edge.player_name()),
+                    None => self.end_match("Draw", "Draw!"),
+                }
+            }
+        }
+    }
+
+    // The jitter is rolled here rather than in `draw` so it comes from the
+    // seeded generator at a fixed rate.
+    fn update_shake(&mut self, dt: f32) {
+        if self.shake_time <= 0.0 {
+            return;
+        }
+
+        self.shake_time -= dt;
+
+        if self.shake_time <= 0.0 {
+            self.shake_time = 0.0;
+            self.shake_offset = Vec2::zero();
+        } else {
+            let strength = self.shake_intensity * self.shake_time / SHAKE_DURATION;
+            self.shake_offset = Vec2::new(
+                self.rng.gen_range(-1.0..=1.0),
+                self.rng.gen_range(-1.0..=1.0),
+            ) * strength;
+        }
+    }
+
+    fn update_game_over(&mut self, ctx: &mut Context) {
+        if !self.counts_for_records() {
+            self.return_to_menu();
+            return;
+        }
+
+        let dt = time::get_delta_time(ctx).as_secs_f32();
+        self.update_shake(dt);
+        self.update_celebration(ctx, dt);
+
+        if is_confirm_pressed(ctx) {
+            // The first press only skips the celebration.
+            if self.celebration_time < CELEBRATION_DURATION {
+                self.celebration_time = CELEBRATION_DURATION;
+                self.particles.clear();
+                return;
+            }
+
+            match self.net.take() {
+                Some(link) => self.start_network_match(ctx, link),
+                None => self.start_match(ctx),
+            }
+        }
+    }
+
+    fn update_celebration(&mut self, ctx: &Context, dt: f32) {
+        update_particles(&mut self.particles, dt);
+
+        if self.celebration_time >= CELEBRATION_DURATION {
+            return;
+        }
+
+        // Confetti stops falling a little early so the last of it lands
+        // before the screen settles.
+        let before = self.celebration_time;
+        self.celebration_time = (self.celebration_time + dt).min(CELEBRATION_DURATION);
+        let raining = CELEBRATION_DURATION - CONFETTI_LIFE;
+        let due = (self.celebration_time.min(raining) * CONFETTI_RATE) as u32
+            - (before.min(raining) * CONFETTI_RATE) as u32;
+
+        let width = self.court_size(ctx).x;
+        for _ in 0..due {
+            spawn_confetti(&mut self.particles, &mut self.rng, width);
+        }
+    }
+
+    fn draw_menu(&mut self, ctx: &mut Context) {
+        let height = get_height(ctx) as f32;
+
+        let text_color = self.theme.text;
+
+        draw_centred_x(ctx, &mut self.title_text, height * 0.25, text_color);
+
+        for (i, text) in self.menu_option_texts.iter_mut().enumerate() {
+            let color = if i == self.menu_index {
+                Color::rgb(1.0, 0.85, 0.2)
+            } else {
+                text_color
+            };
+
+            draw_centred_x(ctx, text, height * 0.45 + i as f32 * 48.0, color);
+        }
+
+        let records = records_message(&self.records);
+        if self.records_text.content() != records {
+            self.records_text.set_content(records);
+        }
+
+        draw_centred_x(ctx, &mut self.records_text, height * 0.7, text_color);
+        draw_centred_x(ctx, &mut self.menu_text, height * 0.8, text_color);
+    }
+
+    fn draw_options(&mut self, ctx: &mut Context) {
+        let height = get_height(ctx) as f32;
+        let text_color = self.theme.text;
+
+        draw_centred_x(ctx, &mut self.options_title_text, height * 0.25, text_color);
+
+        for (i, text) in self.option_texts.iter_mut().enumerate() {
+            let color = if i == self.options_index {
+                Color::rgb(1.0, 0.85, 0.2)
+            } else {
+                text_color
+            };
+
+            draw_centred_x(ctx, text, height * 0.45 + i as f32 * 48.0, color);
+        }
+
+        draw_centred_x(ctx, &mut self.options_hint_text, height * 0.8, text_color);
+    }
+
+    fn draw_outline(&self, ctx: &mut Context, rect: Rectangle, color: Color) {
+        let thickness = 2.0;
+
+        for (position, size) in [
+            (Vec2::new(rect.x, rect.y), Vec2::new(rect.width, thickness)),
+            (
+                Vec2::new(rect.x, rect.bottom() - thickness),
+                Vec2::new(rect.width, thickness),
+            ),
+            (Vec2::new(rect.x, rect.y), Vec2::new(thickness, rect.height)),
+            (
+                Vec2::new(rect.right() - thickness, rect.y),
+                Vec2::new(thickness, rect.height),
+            ),
+        ] {
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(position)
+                    .scale(size)
+                    .color(color),
+            );
+        }
+    }
+
+    // Warmer and more opaque where the ball has spent more time.
+    fn draw_heatmap(&self, ctx: &mut Context) {
+        let court = self.court_size(ctx);
+        let cell = Vec2::new(
+            court.x / HEATMAP_COLUMNS as f32,
+            court.y / HEATMAP_ROWS as f32,
+        );
+
+        for row in 0..HEATMAP_ROWS {
+            for column in 0..HEATMAP_COLUMNS {
+                let heat = self.sim.heatmap.heat(column, row);
+
+                if heat <= 0.0 {
+                    continue;
+                }
+
+                self.pixel.draw(
+                    ctx,
+                    DrawParams::new()
+                        .position(Vec2::new(column as f32 * cell.x, row as f32 * cell.y))
+                        .scale(cell)
+                        .color(Color::rgba(1.0, 1.0 - heat, 0.0, heat * HEATMAP_MAX_ALPHA)),
+                );
+            }
+        }
+    }
+
+    fn draw_net(&self, ctx: &mut Context) {
+        let width = self.court_size(ctx).x;
+        let height = self.court_size(ctx).y;
+
+        let net_width = if self.settings.high_contrast {
+            HIGH_CONTRAST_NET_WIDTH
+        } else {
+            NET_WIDTH
+        };
+        // Timed matches and practice have no match point to build up to.
+        let color = if self.sim.match_mode == MatchMode::Points
+            && self.sim.player2_control != ControlMode::Wall
+        {
+            let leader = self
+                .sim
+                .player_scores()
+                .iter()
+                .map(|&(_, score)| score)
+                .max();
+            let tension = net_tension(leader.unwrap_or(0), 0, self.settings.win_score);
+            lerp_color(self.theme.net, NET_MATCH_POINT_COLOR, tension)
+        } else {
+            self.theme.net
+        };
+        let dash_height = (height / 32.0).max(8.0);
+        let dash_count = (height / (dash_height * 2.0)).ceil() as i32;
+        let gap = (height - dash_height * dash_count as f32) / dash_count as f32;
+
+        for i in 0..dash_count {
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(Vec2::new(
+                        (width - net_width) / 2.0,
+                        gap / 2.0 + i as f32 * (dash_height + gap),
+                    ))
+                    .scale(Vec2::new(net_width, dash_height))
+                    .color(color),
+            );
+        }
+    }
+
+    // Where the AI expects the ball it is chasing to reach its paddle.
+    fn draw_intercept_ghost(&self, ctx: &mut Context) {
+        let Some(ball) = self.sim.incoming_ball(Side::Right) else {
+            return;
+        };
+
+        let y = self.sim.predict_ball_y(Side::Right, self.court_size(ctx).y);
+
+        self.ball_texture.draw(
+            ctx,
+            DrawParams::new()
+                .position(Vec2::new(
+                    self.sim.contact_x(Side::Right, ball),
+                    y - ball.height() / 2.0,
+                ))
+                .color(Color::rgba(1.0, 1.0, 1.0, 0.35)),
+        );
+    }
+
+    // A small square per effect, in a row above the affected paddle.
+    fn draw_effect_icons(&self, ctx: &mut Context, blend: f32) {
+        for paddle in [&self.sim.player1, &self.sim.player2] {
+            let drawn = paddle.drawn_position(blend);
+
+            for (i, effect) in paddle.effects.iter().enumerate() {
+                let position = Vec2::new(
+                    drawn.x + i as f32 * (EFFECT_ICON_SIZE + 4.0),
+                    drawn.y - EFFECT_ICON_SIZE - 4.0,
+                );
+
+                self.pixel.draw(
+                    ctx,
+                    DrawParams::new()
+                        .position(position)
+                        .scale(Vec2::broadcast(EFFECT_ICON_SIZE))
+                        .color(effect.kind.color()),
+                );
+            }
+        }
+    }
+
+    // A strip along each recharging paddle's court-facing side that grows
+    // back to full length as the dash recharges.
+    fn draw_dash_meters(&self, ctx: &mut Context, paddle_count: usize, blend: f32) {
+        let paddles = [
+            (&self.sim.player1, Edge::Left),
+            (&self.sim.player2, Edge::Right),
+            (&self.sim.player3, Edge::Top),
+            (&self.sim.player4, Edge::Bottom),
+        ];
+
+        for (paddle, edge) in paddles.into_iter().take(paddle_count) {
+            if paddle.dash_cooldown <= 0.0 {
+                continue;
+            }
+
+            let ready = 1.0 - paddle.dash_cooldown / DASH_COOLDOWN;
+            let bounds = paddle.drawn_bounds(blend);
+            let (position, size) = match edge {
+                Edge::Left => (
+                    Vec2::new(bounds.right() + DASH_METER_THICKNESS, bounds.y),
+                    Vec2::new(DASH_METER_THICKNESS, bounds.height * ready),
+                ),
+                Edge::Right => (
+                    Vec2::new(bounds.x - DASH_METER_THICKNESS * 2.0, bounds.y),
+                    Vec2::new(DASH_METER_THICKNESS, bounds.height * ready),
+                ),
+                Edge::Top => (
+                    Vec2::new(bounds.x, bounds.bottom() + DASH_METER_THICKNESS),
+                    Vec2::new(bounds.width * ready, DASH_METER_THICKNESS),
+                ),
+                Edge::Bottom => (
+                    Vec2::new(bounds.x, bounds.y - DASH_METER_THICKNESS * 2.0),
+                    Vec2::new(bounds.width * ready, DASH_METER_THICKNESS),
+                ),
+            };
+
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(position)
+                    .scale(size)
+                    .color(self.theme.text.with_alpha(0.5)),
+            );
+        }
+    }
+
+    // Sits just above the serving paddle, filling away from the wall.
+    fn draw_charge_meter(&self, ctx: &mut Context) {
+        let Some(side) = self.sim.charging_server() else {
+            return;
+        };
+
+        let (paddle, x) = match side {
+            Side::Left => (&self.sim.player1, self.sim.player1.position.x),
+            Side::Right => (
+                &self.sim.player2,
+                self.sim.player2.position.x + self.sim.player2.width() - CHARGE_METER_WIDTH,
+            ),
+        };
+        let position = Vec2::new(x, paddle.position.y - CHARGE_METER_HEIGHT * 2.0);
+
+        self.pixel.draw(
+            ctx,
+            DrawParams::new()
+                .position(position)
+                .scale(Vec2::new(CHARGE_METER_WIDTH, CHARGE_METER_HEIGHT))
+                .color(Color::rgba(1.0, 1.0, 1.0, 0.3)),
+        );
+
+        let filled = CHARGE_METER_WIDTH * self.sim.serve_charge;
+        let fill_x = match side {
+            Side::Left => position.x,
+            Side::Right => position.x + CHARGE_METER_WIDTH - filled,
+        };
+
+        self.pixel.draw(
+            ctx,
+            DrawParams::new()
+                .position(Vec2::new(fill_x, position.y))
+                .scale(Vec2::new(filled, CHARGE_METER_HEIGHT))
+                .color(Color::rgb(1.0, 0.85, 0.2)),
+        );
+    }
+
+    fn draw_playing(&mut self, ctx: &mut Context) {
+        graphics::set_transform_matrix(ctx, self.court_transform(ctx));
+        self.draw_court(ctx);
+        graphics::set_transform_matrix(ctx, Mat4::translation_2d(self.shake_offset));
+
+        self.draw_scores(ctx);
+
+        if self.sim.point_flash > 0.0 {
+            draw_centred(ctx, &mut self.point_text, self.theme.text);
+        } else if self.sim.intro_countdown > 0.0 || self.sim.serve_countdown > 0.0 {
+            if self.sim.intro_countdown > 0.0 {
+                let y = get_height(ctx) as f32 / 2.0 - 120.0;
+                draw_centred_x(ctx, &mut self.intro_text, y, self.theme.text);
+            }
+
+            let seconds = self
+                .sim
+                .intro_countdown
+                .max(self.sim.serve_countdown)
+                .ceil()
+                .to_string();
+
+            if self.countdown_text.content() != seconds {
+                self.countdown_text.set_content(seconds);
+            }
+
+            draw_centred(ctx, &mut self.countdown_text, self.theme.text);
+        }
+
+        self.draw_playing_overlay(ctx);
+    }
+
+    // Everything on the court, in court coordinates.
+    fn draw_court(&mut self, ctx: &mut Context) {
+        let blend = time::get_blend_factor(ctx);
+        self.draw_net(ctx);
+
+        if self.show_heatmap {
+            self.draw_heatmap(ctx);
+        }
+
+        let paddle_count = if self.sim.player2_control == ControlMode::Wall {
+            1
+        } else if self.sim.four_player {
+            4
+        } else {
+            2
+        };
+        let paddles = [
+            (&self.sim.player1, &self.player1_texture),
+            (&self.sim.player2, &self.player2_texture),
+            (&self.sim.player3, &self.player3_texture),
+            (&self.sim.player4, &self.player4_texture),
+        ];
+
+        // Only the winner's paddle celebrates; it grows about its centre.
+        let winner = match self.scene {
+            Scene::GameOver => winner_index(&self.winner),
+            _ => None,
+        };
+        let celebration_scale = celebration_paddle_scale(self.celebration_time);
+
+        for (index, (paddle, texture)) in paddles.into_iter().take(paddle_count).enumerate() {
+            let scale = if winner == Some(index) {
+                celebration_scale
+            } else {
+                1.0
+            };
+            let size = Vec2::new(paddle.width(), paddle.height());
+            let position = paddle.drawn_position(blend) - size * (scale - 1.0) / 2.0;
+
+            if self.settings.high_contrast {
+                self.pixel.draw(
+                    ctx,
+                    DrawParams::new().position(position).scale(size * scale),
+                );
+            } else {
+                texture.draw(
+                    ctx,
+                    DrawParams::new().position(position).scale(Vec2::new(
+                        scale,
+                        paddle.height() / texture.height() as f32 * scale,
+                    )),
+                );
+            }
+        }
+
+        self.draw_dash_meters(ctx, paddle_count, blend);
+        self.draw_effect_icons(ctx, blend);
+
+        let court = self.court_size(ctx);
+        for (side, active) in [
+            (Side::Left, self.sim.player1_shield_active),
+            (Side::Right, self.sim.player2_shield_active),
+        ] {
+            if active && !self.sim.four_player {
+                let shield = shield_bounds(side, court);
+
+                self.pixel.draw(
+                    ctx,
+                    DrawParams::new()
+                        .position(Vec2::new(shield.x, shield.y))
+                        .scale(Vec2::new(shield.width, shield.height))
+                        .color(PowerUpKind::Shield.color().with_alpha(0.4)),
+                );
+            }
+        }
+
+        if self.sim.player2_control == ControlMode::Wall {
+            let wall = practice_wall(self.court_size(ctx).x, self.court_size(ctx).y);
+
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(Vec2::new(wall.x, wall.y))
+                    .scale(Vec2::new(wall.width, wall.height))
+                    .color(self.theme.text),
+            );
+        }
+
+        if self.sim.court == Court::Bricks {
+            for brick in self.sim.bricks.iter().filter(|brick| brick.alive) {
+                self.pixel.draw(
+                    ctx,
+                    DrawParams::new()
+                        .position(Vec2::new(brick.bounds.x, brick.bounds.y))
+                        .scale(Vec2::new(brick.bounds.width, brick.bounds.height))
+                        .color(Color::rgb(0.95, 0.6, 0.2)),
+                );
+            }
+        }
+
+        if self.sim.court.has_obstacle() {
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(Vec2::new(self.sim.obstacle.x, self.sim.obstacle.y))
+                    .scale(Vec2::new(self.sim.obstacle.width, self.sim.obstacle.height))
+                    .color(Color::rgb(0.55, 0.55, 0.65)),
+            );
+        }
+
+        if let Some(power_up) = &self.sim.power_up {
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(power_up.position)
+                    .scale(Vec2::broadcast(POWER_UP_SIZE))
+                    .color(power_up.kind.color()),
+            );
+        }
+
+        for particle in &self.particles {
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(particle.position - PARTICLE_SIZE / 2.0)
+                    .scale(Vec2::broadcast(PARTICLE_SIZE))
+                    .color(Color::rgba(
+                        1.0,
+                        1.0,
+                        1.0,
+                        (particle.life / PARTICLE_LIFE).min(1.0),
+                    )),
+            );
+        }
+
+        // Oldest positions come first and are drawn the faintest.
+        for (i, position) in self.sim.ball_trail.iter().enumerate() {
+            let alpha = (i + 1) as f32 / (TRAIL_LENGTH + 1) as f32 * 0.5;
+
+            self.ball_texture.draw(
+                ctx,
+                DrawParams::new()
+                    .position(*position)
+                    .color(Color::rgba(1.0, 1.0, 1.0, alpha)),
+            );
+        }
+
+        // Drawn about its centre so it spins in place; in high-contrast mode
+        // only the sprite grows, collisions still use the texture size.
+        let (ball_scale, ball_color) = if self.settings.high_contrast {
+            (HIGH_CONTRAST_BALL_SCALE, Color::rgb(1.0, 1.0, 0.0))
+        } else {
+            (1.0, Color::WHITE)
+        };
+
+        for ball in &self.sim.balls {
+            self.ball_texture.draw(
+                ctx,
+                DrawParams::new()
+                    .position(
+                        ball.drawn_position(blend) + Vec2::new(ball.width(), ball.height()) / 2.0,
+                    )
+                    .origin(Vec2::new(ball.width(), ball.height()) / 2.0)
+                    .rotation(ball.rotation)
+                    .scale(Vec2::broadcast(ball_scale))
+                    .color(ball_color),
+            );
+        }
+
+        if self.show_bounds {
+            self.draw_outline(ctx, self.sim.player1.bounds(), Color::rgb(1.0, 0.0, 1.0));
+            self.draw_outline(ctx, self.sim.player2.bounds(), Color::rgb(1.0, 0.0, 1.0));
+            for ball in &self.sim.balls {
+                self.draw_outline(ctx, ball.bounds(), Color::rgb(0.0, 1.0, 0.0));
+            }
+        }
+
+        if self.sim.point_flash <= 0.0
+            && self.sim.serve_countdown > 0.0
+            && self.sim.serve_charge > 0.0
+        {
+            self.draw_charge_meter(ctx);
+        }
+
+        if self.show_debug && self.sim.player2_control == ControlMode::Ai {
+            self.draw_intercept_ghost(ctx);
+        }
+    }
+
+    // The HUD drawn over the court, in screen coordinates.
+    fn draw_playing_overlay(&mut self, ctx: &mut Context) {
+        if self.sim.demo {
+            let y = get_height(ctx) as f32 - 80.0;
+            draw_centred_x(ctx, &mut self.demo_text, y, self.theme.text);
+        } else if self.playback.is_some() {
+            let y = get_height(ctx) as f32 - 80.0;
+            draw_centred_x(ctx, &mut self.replay_text, y, self.theme.text);
+        }
+
+        if self.paused {
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .scale(Vec2::new(get_width(ctx) as f32, get_height(ctx) as f32))
+                    .color(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+            );
+
+            draw_centred(ctx, &mut self.paused_text, self.theme.text);
+        }
+    }
+
+    // Everything that appears in a screenshot; toasts are drawn on top.
+    fn draw_frame(&mut self, ctx: &mut Context) {
+        graphics::clear(ctx, self.theme.background);
+        self.draw_background(ctx);
+        graphics::set_transform_matrix(ctx, Mat4::translation_2d(self.shake_offset));
+
+        match self.scene {
+            Scene::Menu => self.draw_menu(ctx),
+            Scene::Options => self.draw_options(ctx),
+            Scene::Playing => self.draw_playing(ctx),
+            Scene::GameOver => self.draw_game_over(ctx),
+        }
+
+        graphics::reset_transform_matrix(ctx);
+
+        self.draw_debug_overlay(ctx);
+    }
+
+    // High contrast always keeps the flat background.
+    fn draw_background(&mut self, ctx: &mut Context) {
+        if self.settings.high_contrast {
+            return;
+        }
+
+        match self.settings.background {
+            BackgroundMode::Flat => {}
+            BackgroundMode::Gradient => self.draw_gradient(ctx),
+            BackgroundMode::Starfield => self.draw_stars(ctx),
+        }
+    }
+
+    fn draw_stars(&self, ctx: &mut Context) {
+        for star in &self.stars {
+            self.pixel.draw(
+                ctx,
+                DrawParams::new()
+                    .position(star.position)
+                    .scale(Vec2::broadcast(STAR_SIZE))
+                    .color(Color::rgba(1.0, 1.0, 1.0, star.brightness)),
+            );
+        }
+    }
+
+    fn draw_gradient(&mut self, ctx: &mut Context) {
+        let width = get_width(ctx) as f32;
+        let height = get_height(ctx) as f32;
+        let (top, bottom) = background_gradient(self.theme.background, self.background_time);
+        let vertices = [
+            Vertex::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), top),
+            Vertex::new(Vec2::new(width, 0.0), Vec2::new(1.0, 0.0), top),
+            Vertex::new(Vec2::new(width, height), Vec2::new(1.0, 1.0), bottom),
+            Vertex::new(Vec2::new(0.0, height), Vec2::new(0.0, 1.0), bottom),
+        ];
+
+        self.background_mesh
+            .vertex_buffer()
+            .set_data(ctx, &vertices, 0);
+        self.background_mesh.draw(ctx, Vec2::zero());
+    }
+
+    // Renders the frame again into an off-screen canvas and writes that out, so
+    // the capture doesn't depend on the window's back buffer.
+    fn capture_screenshot(&mut self, ctx: &mut Context) -> Result<String, String> {
+        let (width, height) = (get_width(ctx), get_height(ctx));
+        let canvas = Canvas::new(ctx, width, height).map_err(|e| e.to_string())?;
+
+        graphics::set_canvas(ctx, &canvas);
+        self.draw_frame(ctx);
+        graphics::reset_canvas(ctx);
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_secs();
+        let path = format!("screenshot-{}.png", timestamp);
+        let data = canvas.get_data(ctx);
+
+        image::save_buffer(
+            &path,
+            data.as_bytes(),
+            width as u32,
+            height as u32,
+            image::ColorType::Rgba8,
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(path)
+    }
+
+    fn poll_textures(&mut self, ctx: &mut Context, dt: f32) {
+        if self.watched_textures.is_empty() {
+            return;
+        }
+
+        self.texture_poll_time += dt;
+        if self.texture_poll_time < TEXTURE_POLL_INTERVAL {
+            return;
+        }
+        self.texture_poll_time = 0.0;
+
+        let mut reloaded = Vec::new();
+        for watched in &mut self.watched_textures {
+            let modified = file_modified(watched.path);
+
+            // A file that's gone, or mid-save, keeps the texture it had.
+            if modified.is_none() || modified == watched.modified {
+                continue;
+            }
+            watched.modified = modified;
+
+            match Texture::new(ctx, watched.path) {
+                Ok(texture) => reloaded.push((watched.slot, watched.path, texture)),
+                Err(e) => eprintln!("Could not reload {}: {}", watched.path, e),
+            }
+        }
+
+        if reloaded.is_empty() {
+            return;
+        }
+
+        for (slot, path, texture) in reloaded {
+            let size = texture_size(&texture);
+            match slot {
+                TextureSlot::Player1 => {
+                    self.player1_texture = texture;
+                    self.sim.player1.base_size = size;
+                }
+                TextureSlot::Player2 => {
+                    self.player2_texture = texture;
+                    self.sim.player2.base_size = size;
+                }
+                TextureSlot::Ball => {
+                    self.ball_texture = texture;
+                    for ball in &mut self.sim.balls {
+                        ball.base_size = size;
+                    }
+                }
+            }
+            eprintln!("Reloaded {}", path);
+        }
+
+        // Positions stay put; a new size only pulls things back in bounds.
+        self.fit_to_window(ctx);
+    }
+
+    fn push_toast(&mut self, message: &str) {
+        // Mashing a key restarts its toast rather than queueing copies.
+        if let Some((last, remaining)) = self.toasts.back_mut() {
+            if last == message {
+                *remaining = TOAST_DURATION;
+                return;
+            }
+        }
+
+        if self.toasts.len() == TOAST_QUEUE_LIMIT {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back((message.to_owned(), TOAST_DURATION));
+    }
+
+    fn draw_toast(&mut self, ctx: &mut Context) {
+        let Some((message, remaining)) = self.toasts.front() else {
+            return;
+        };
+
+        if self.toast_text.content() != message {
+            self.toast_text.set_content(message.as_str());
+        }
+
+        let y = get_height(ctx) as f32 - 160.0;
+        let alpha = (remaining / TOAST_DURATION * 4.0).min(1.0);
+        draw_centred_x(
+            ctx,
+            &mut self.toast_text,
+            y,
+            self.theme.text.with_alpha(alpha),
+        );
+    }
+
+    // Frame times are sampled in `draw`, where the delta is the real time since
+    // the last frame rather than the fixed update step.
+    fn draw_debug_overlay(&mut self, ctx: &mut Context) {
+        if self.frame_times.len() == FRAME_TIME_SAMPLES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times
+            .push_back(time::get_delta_time(ctx).as_secs_f32());
+
+        if !self.show_debug {
+            return;
+        }
+
+        let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32;
+        let fps = if average > 0.0 { 1.0 / average } else { 0.0 };
+
+        self.debug_text.set_content(format!(
+            "FPS: {:.0}\nFrame: {:.2} ms",
+            fps,
+            average * 1000.0
+        ));
+        self.debug_text.draw(ctx, Vec2::new(8.0, 8.0));
+    }
+
+    fn draw_game_over(&mut self, ctx: &mut Context) {
+        self.draw_playing(ctx);
+
+        // Dim the court so the recap stands out.
+        self.pixel.draw(
+            ctx,
+            DrawParams::new()
+                .scale(Vec2::new(get_width(ctx) as f32, get_height(ctx) as f32))
+                .color(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+        );
+
+        let scale = celebration_text_scale(self.celebration_time);
+        if scale >= 1.0 {
+            draw_centred(ctx, &mut self.winner_text, self.theme.text);
+            return;
+        }
+
+        let bounds = self.winner_text.get_bounds(ctx).unwrap_or_default();
+        let size = Vec2::new(bounds.width, bounds.height) * scale;
+        let window = Vec2::new(get_width(ctx) as f32, get_height(ctx) as f32);
+        self.winner_text.draw(
+            ctx,
+            DrawParams::new()
+                .position((window - size) / 2.0)
+                .scale(Vec2::broadcast(scale))
+                .color(self.theme.text),
+        );
+    }
+
+    fn draw_confirm_quit(&mut self, ctx: &mut Context) {
+        self.pixel.draw(
+            ctx,
+            DrawParams::new()
+                .scale(Vec2::new(get_width(ctx) as f32, get_height(ctx) as f32))
+                .color(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+        );
+
+        draw_centred(ctx, &mut self.confirm_quit_text, self.theme.text);
+    }
+
+    // Returns whether the dialog is up, in which case nothing else updates.
+    fn update_confirm_quit(&mut self, ctx: &mut Context) -> bool {
+        if !self.confirm_quit {
+            if input::is_key_pressed(ctx, Key::Escape) {
+                self.confirm_quit = true;
+                return true;
+            }
+
+            return false;
+        }
+
+        if input::is_key_pressed(ctx, Key::Y) {
+            window::quit(ctx);
+        } else if input::is_key_pressed(ctx, Key::N) || input::is_key_pressed(ctx, Key::Escape) {
+            self.confirm_quit = false;
+        }
+
+        true
+    }
+}
+
+impl State for GameState {
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        if matches!(self.net_role(), Some(NetRole::Client | NetRole::Spectator)) {
+            self.show_snapshot(ctx);
+        }
+
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+
+            match self.capture_screenshot(ctx) {
+                Ok(path) => self.push_toast(&format!("Saved screenshot {}", path)),
+                Err(e) => {
+                    eprintln!("Could not save screenshot: {}", e);
+                    self.push_toast("Screenshot failed");
+                }
+            }
+        }
+
+        self.draw_frame(ctx);
+        self.draw_toast(ctx);
+
+        if self.confirm_quit {
+            self.draw_confirm_quit(ctx);
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result {
+        if self.update_confirm_quit(ctx) {
+            return Ok(());
+        }
+
+        self.frame += 1;
+        if self.frame.is_multiple_of(EVENT_LOG_FLUSH_FRAMES) {
+            if let Some(Err(e)) = self.event_log.as_mut().map(EventLog::flush) {
+                eprintln!("Stopped logging events: {}", e);
+                self.event_log = None;
+            }
+        }
+
+        let dt = time::get_delta_time(ctx).as_secs_f32();
+        advance_toasts(&mut self.toasts, dt);
+        self.poll_textures(ctx, dt);
+        self.background_time = (self.background_time + dt) % BACKGROUND_CYCLE;
+        scroll_stars(
+            &mut self.stars,
+            dt,
+            get_width(ctx) as f32,
+            get_height(ctx) as f32,
+        );
+
+        // Global toggles are momentary; see `Trigger`.
+        if input::is_key_pressed(ctx, Key::F12) {
+            self.screenshot_requested = true;
+        }
+
+        // Any input ends the demo or replay, and is used up doing so.
+        if !self.counts_for_records() && is_any_input_pressed(ctx) {
+            self.return_to_menu();
+            return Ok(());
+        }
+
+        if input::is_key_pressed(ctx, Key::M) {
+            self.toggle_music_mute();
+        }
+
+        if input::is_key_pressed(ctx, Key::T) {
+            self.settings.theme = self.settings.theme.next();
+            self.theme = active_theme(&self.settings);
+            self.push_toast(&format!("Theme: {}", self.settings.theme.name()));
+        }
+
+        if input::is_key_pressed(ctx, Key::H) {
+            self.settings.high_contrast = !self.settings.high_contrast;
+            self.theme = active_theme(&self.settings);
+        }
+
+        if input::is_key_pressed(ctx, Key::B) {
+            self.settings.background = self.settings.background.next();
+        }
+
+        // A network match can't be saved or replaced by a loaded one.
+        if input::is_key_pressed(ctx, Key::F5)
+            && !matches!(self.scene, Scene::Menu | Scene::Options)
+            && self.counts_for_records()
+            && self.net.is_none()
+        {
+            self.save_game();
+        }
+
+        if input::is_key_pressed(ctx, Key::F9) && self.net.is_none() {
+            self.load_game(ctx);
+        }
+
+        if input::is_key_pressed(ctx, Key::F3) {
+            self.show_debug = !self.show_debug;
+        }
+
+        if input::is_key_pressed(ctx, Key::F4) {
+            self.show_bounds = !self.show_bounds;
+        }
+
+        if input::is_key_pressed(ctx, Key::F6) {
+            self.show_heatmap = !self.show_heatmap;
+        }
+
+        if input::is_key_pressed(ctx, Key::F11) {
+            self.fullscreen = !self.fullscreen;
+            window::set_fullscreen(ctx, self.fullscreen)?;
+        }
+
+        let window_size = window::get_size(ctx);
+
+        if window_size != self.window_size {
+            self.window_size = window_size;
+            self.fit_to_window(ctx);
+        }
+
+        let role = self.net_role();
+
+        if role == Some(NetRole::Host) {
+            if let Some(link) = &mut self.net {
+                link.admit();
+            }
+            self.receive_remote_input();
+        }
+
+        match (self.scene, self.net_role()) {
+            (Scene::Playing | Scene::GameOver, Some(NetRole::Client | NetRole::Spectator)) => {
+                self.update_client(ctx)
+            }
+            (Scene::Menu, _) => self.update_menu(ctx),
+            (Scene::Options, _) => self.update_options(ctx),
+            (Scene::Playing, _) => self.update_playing(ctx),
+            (Scene::GameOver, _) => self.update_game_over(ctx),
+        }
+
+        if role == Some(NetRole::Host) && self.net_role() == Some(NetRole::Host) {
+            self.send_net_state();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_overlap_pushes_ball_out_of_left_paddle() {
+        let paddle = Rectangle::new(16.0, 400.0, 32.0, 160.0);
+        let ball_width = 24.0;
+        let mut velocity_x = -600.0;
+
+        let mut ball_x = paddle_contact(ball_width, velocity_x, paddle, Side::Left)
+            .expect("ball moving into the paddle should hit it");
+        velocity_x = -velocity_x;
+        ball_x += velocity_x / 60.0;
+
+        let ball = Rectangle::new(ball_x, 460.0, ball_width, ball_width);
+        assert!(!ball.intersects(&paddle));
+        assert!(velocity_x > 0.0);
+        assert_eq!(
+            paddle_contact(ball_width, velocity_x, paddle, Side::Left),
+            None
+        );
+    }
+
+    #[test]
+    fn net_messages_survive_the_wire_in_pieces() {
+        let state = NetState {
+            tick: 42,
+            player1: Vec2::new(16.0, 300.5),
+            player2: Vec2::new(1872.0, 12.0),
+            player1_height_scale: 1.5,
+            player2_height_scale: 1.0,
+            point_flash: 0.0,
+            serve_countdown: 2.5,
+            intro_countdown: 0.0,
+            balls: vec![Vec2::new(960.0, 540.0), Vec2::new(-3.0, 7.25)],
+            player1_score: 3,
+            player2_score: 9,
+            winner: 2,
+        };
+        let input = RemoteInput {
+            direction: -1.0,
+            serve: true,
+            dash: false,
+        };
+        let hello = NetMessage::Hello { spectator: true };
+        let mut wire = hello.encode();
+        wire.extend(NetMessage::State(state.clone()).encode());
+        wire.extend(NetMessage::Input(input).encode());
+
+        // Bytes arrive a few at a time; only whole frames come out.
+        let mut buffer = Vec::new();
+        let mut received = Vec::new();
+        for chunk in wire.chunks(5) {
+            buffer.extend(chunk);
+            received.extend(take_messages(&mut buffer).unwrap());
+        }
+
+        assert_eq!(
+            received,
+            vec![hello, NetMessage::State(state), NetMessage::Input(input)]
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn snapshots_blend_the_newest_two_by_tick() {
+        let at = |tick, x| NetState {
+            tick,
+            player1: Vec2::new(x, 0.0),
+            balls: vec![Vec2::new(x * 2.0, 0.0)],
+            ..NetState::default()
+        };
+        let mut buffer = SnapshotBuffer::default();
+
+        assert_eq!(buffer.sample(0.5), None);
+        assert!(buffer.push(at(10, 0.0)));
+        assert!(buffer.push(at(12, 100.0)));
+        assert!(!buffer.push(at(11, 50.0)));
+
+        // The gap is two ticks, so one and a half in is three quarters.
+        buffer.advance();
+        let state = buffer.sample(0.5).unwrap();
+        assert_eq!(state.tick, 12);
+        assert_eq!(state.player1.x, 75.0);
+        assert_eq!(state.balls[0].x, 150.0);
+
+        // A late snapshot holds at the newest rather than overshooting.
+        buffer.advance();
+        buffer.advance();
+        assert_eq!(buffer.sample(0.9).unwrap().player1.x, 100.0);
+
+        for tick in 13..20 {
+            buffer.push(at(tick, 0.0));
+        }
+        assert_eq!(buffer.snapshots.len(), SNAPSHOT_CAPACITY);
+    }
+
+    #[test]
+    fn a_rematch_starts_the_snapshots_over() {
+        let at = |tick| NetState {
+            tick,
+            ..NetState::default()
+        };
+        let mut buffer = SnapshotBuffer::default();
+
+        assert!(buffer.push(at(100)));
+        buffer.clear();
+        assert!(buffer.push(at(0)));
+        assert!(buffer.push(at(1)));
+        assert_eq!(buffer.sample(0.0).unwrap().tick, 1);
+    }
+
+    #[test]
+    fn predicted_paddle_only_gives_way_when_clearly_wrong() {
+        // A moving paddle is expected to lead the host's copy.
+        assert_eq!(reconcile(500.0, 450.0, true), 500.0);
+        // Too far out and it's pulled back to the edge of the allowance.
+        assert_eq!(
+            reconcile(800.0, 450.0, true),
+            450.0 + NET_RECONCILE_DISTANCE
+        );
+        // Once it stops it settles onto the host's position.
+        let mut y = 500.0;
+        for _ in 0..60 {
+            y = reconcile(y, 450.0, false);
+        }
+        assert!((y - 450.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn event_rows_are_csv() {
+        assert_eq!(
+            event_row(120, BallEvent::Paddle, Vec2::new(48.25, 500.0), 812.345),
+            "120,paddle,48.2,500.0,812.3"
+        );
+        assert_eq!(
+            event_row(7, BallEvent::Score, Vec2::new(-10.0, 3.0), 0.0),
+            "7,score,-10.0,3.0,0.0"
+        );
+    }
+
+    #[test]
+    fn heatmap_counts_time_in_each_cell() {
+        let court = Vec2::new(1920.0, 1080.0);
+        let mut heatmap = Heatmap::new();
+        assert_eq!(heatmap.heat(0, 0), 0.0);
+
+        for _ in 0..4 {
+            heatmap.record(Vec2::new(10.0, 10.0), court);
+        }
+        heatmap.record(Vec2::new(1919.0, 1079.0), court);
+        heatmap.record(Vec2::new(-5.0, 500.0), court);
+        heatmap.record(Vec2::new(1920.0, 500.0), court);
+
+        assert_eq!(heatmap.heat(0, 0), 1.0);
+        assert_eq!(heatmap.heat(HEATMAP_COLUMNS - 1, HEATMAP_ROWS - 1), 0.25);
+        assert_eq!(heatmap.counts.iter().flatten().sum::<u32>(), 5);
+    }
+
+    #[test]
+    fn broken_net_frames_are_errors() {
+        let mut unknown = vec![0, 0, 0, 1, 99];
+        assert!(take_messages(&mut unknown).is_err());
+
+        let mut too_long = (NET_MAX_MESSAGE as u32 + 1).to_be_bytes().to_vec();
+        assert!(take_messages(&mut too_long).is_err());
+
+        let mut short = vec![0, 0, 0, 2, NET_INPUT, 0];
+        assert!(take_messages(&mut short).is_err());
+
+        let mut nan = NetMessage::Input(RemoteInput {
+            direction: f32::NAN,
+            ..RemoteInput::default()
+        })
+        .encode();
+        assert!(take_messages(&mut nan).is_err());
+
+        let mut infinite = NetMessage::State(NetState {
+            balls: vec![Vec2::new(f32::INFINITY, 0.0)],
+            ..NetState::default()
+        })
+        .encode();
+        assert!(take_messages(&mut infinite).is_err());
+    }
+
+    #[test]
+    fn settings_fill_missing_fields_with_defaults() {
+        let settings =
+            Settings::parse("fullscreen = false\nwin_score = 5\ntheme = \"neon\"\n").unwrap();
+
+        assert!(settings.validate().is_ok());
+        assert_eq!(
+            settings,
+            Settings {
+                fullscreen: false,
+                win_score: 5,
+                theme: ThemeKind::Neon,
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn hex_colors_parse_with_or_without_a_hash() {
+        let close = |a: Color, b: Color| {
+            (a.r - b.r).abs() < 0.002 && (a.g - b.g).abs() < 0.002 && (a.b - b.b).abs() < 0.002
+        };
+
+        assert!(close(parse_hex_color("#6495ED").unwrap(), CORNFLOWER_BLUE));
+        assert!(close(parse_hex_color("6495ed").unwrap(), CORNFLOWER_BLUE));
+        assert_eq!(parse_hex_color("#000000"), Ok(Color::BLACK));
+        assert_eq!(parse_hex_color("FFFFFF"), Ok(Color::WHITE));
+        assert_eq!(
+            format_hex_color(parse_hex_color("#336699").unwrap()),
+            "#336699"
+        );
+
+        for invalid in [
+            "", "#", "#12345", "#1234567", "#GG0000", "##123456", "12 456", "#ééé",
+        ] {
+            assert!(parse_hex_color(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn background_color_overrides_the_theme_or_falls_back() {
+        let settings = Settings::parse("background_color = \"#336699\"\n").unwrap();
+        assert_eq!(
+            active_theme(&settings).background,
+            parse_hex_color("#336699").unwrap()
+        );
+        let saved = toml::to_string_pretty(&settings).unwrap();
+        assert!(saved.contains("background_color = \"#336699\""));
+        assert_eq!(Settings::parse(&saved).unwrap(), settings);
+
+        let invalid = Settings::parse("background_color = \"blue\"\n").unwrap();
+        assert_eq!(invalid.background_color, Some(CORNFLOWER_BLUE));
+
+        // High contrast keeps its black background regardless.
+        let contrast = Settings {
+            high_contrast: true,
+            ..settings
+        };
+        assert_eq!(active_theme(&contrast).background, Color::BLACK);
+    }
+
+    #[test]
+    fn hue_shift_keeps_greys_and_wraps_around() {
+        let grey = Color::rgb(0.5, 0.5, 0.5);
+        let blue = Color::rgb(0.392, 0.584, 0.929);
+
+        let shifted = shift_hue(grey, 45.0);
+        assert!((shifted.r - 0.5).abs() < 1e-5 && (shifted.b - 0.5).abs() < 1e-5);
+
+        let wrapped = shift_hue(blue, 360.0);
+        assert!((wrapped.r - blue.r).abs() < 1e-5);
+        assert!((wrapped.g - blue.g).abs() < 1e-5);
+        assert!((wrapped.b - blue.b).abs() < 1e-5);
+    }
+
+    #[test]
+    fn vertical_court_puts_player_one_at_the_bottom() {
+        let screen = Vec2::new(640.0, 480.0);
+        let court = court_size(screen, Orientation::Vertical);
+        assert_eq!(court, Vec2::new(480.0, 640.0));
+
+        let m = court_transform(screen, Orientation::Vertical);
+        let map = |x: f32, y: f32| {
+            let p = m * tetra::math::Vec4::new(x, y, 0.0, 1.0);
+            Vec2::new(p.x, p.y)
+        };
+
+        // Player 1's end of the court is the bottom of the screen, player 2's
+        // the top, and the court's top wall is the screen's left edge.
+        let bottom = map(0.0, court.y / 2.0);
+        let top = map(court.x, court.y / 2.0);
+        let left = map(court.x / 2.0, 0.0);
+        assert!((bottom - Vec2::new(320.0, 480.0)).magnitude() < 1e-3);
+        assert!((top - Vec2::new(320.0, 0.0)).magnitude() < 1e-3);
+        assert!((left - Vec2::new(0.0, 240.0)).magnitude() < 1e-3);
+
+        let identity = court_transform(screen, Orientation::Horizontal);
+        assert_eq!(identity, Mat4::identity());
+    }
+
+    #[test]
+    fn stars_wrap_around_the_screen_edges() {
+        let mut stars = vec![
+            Star {
+                position: Vec2::new(5.0, 100.0),
+                layer_speed: 20.0,
+                brightness: 1.0,
+            },
+            Star {
+                position: Vec2::new(1500.0, 900.0),
+                layer_speed: 140.0,
+                brightness: 1.0,
+            },
+        ];
+
+        scroll_stars(&mut stars, 0.5, 1280.0, 720.0);
+
+        assert_eq!(stars[0].position, Vec2::new(1275.0, 100.0));
+        assert_eq!(stars[1].position, Vec2::new(150.0, 180.0));
+    }
+
+    #[test]
+    fn top_paddle_returns_the_ball_downwards() {
+        let paddle = Rectangle::new(880.0, 16.0, 160.0, 32.0);
+        let ball = Rectangle::new(900.0, 40.0, 24.0, 24.0);
+        let velocity = Vec2::new(100.0, -600.0);
+
+        let y = horizontal_paddle_contact(ball.height, velocity.y, paddle, Edge::Top)
+            .expect("ball moving into the paddle should hit it");
+        assert_eq!(y, paddle.y + paddle.height);
+        assert_eq!(
+            horizontal_paddle_contact(ball.height, -velocity.y, paddle, Edge::Top),
+            None
+        );
+
+        // Hit left of centre, so it comes back down heading left.
+        let returned = horizontal_paddle_return(velocity, ball, paddle);
+        assert!(returned.y > 0.0);
+        assert!(returned.x < velocity.x);
+        assert_eq!(Edge::Top.scorer(), None);
+        assert_eq!(Edge::Right.scorer(), Some(Side::Left));
+    }
+
+    #[test]
+    fn centre_hit_comes_back_faster_and_flatter() {
+        let paddle = Vec2::new(32.0, 540.0);
+        let velocity = resolve_paddle_hit(Vec2::new(-600.0, 100.0), paddle, paddle, 160.0);
+
+        assert!((velocity.x - (600.0 + BALL_ACC) * SWEET_SPOT_SPEEDUP).abs() < 0.01);
+        assert!((velocity.y - 100.0 * SWEET_SPOT_STRAIGHTEN).abs() < 0.01);
+    }
+
+    #[test]
+    fn top_edge_hit_trades_speed_for_upward_spin() {
+        let paddle = Vec2::new(32.0, 540.0);
+        let top = Vec2::new(40.0, 460.0);
+        let velocity = resolve_paddle_hit(Vec2::new(-600.0, 0.0), top, paddle, 160.0);
+
+        assert!((velocity.x - (600.0 + BALL_ACC) * EDGE_SPEED_FACTOR).abs() < 0.01);
+        assert!((velocity.y + PADDLE_SPIN * MAX_HIT_OFFSET * (1.0 + EDGE_SPIN_BONUS)).abs() < 0.01);
+
+        // Clipping the very corner spins no harder, and a fast steep ball is
+        // still sent back across at a legal angle and speed.
+        let corner = Vec2::new(40.0, 440.0);
+        assert_eq!(
+            resolve_paddle_hit(Vec2::new(-600.0, 0.0), corner, paddle, 160.0),
+            velocity
+        );
+        let steep = resolve_paddle_hit(Vec2::new(-1500.0, -2000.0), top, paddle, 160.0);
+        assert!(steep.magnitude() <= MAX_BALL_SPEED + 0.01);
+        assert!(steep.y.abs().atan2(steep.x).to_degrees() <= MAX_BOUNCE_ANGLE + 0.01);
+    }
+
+    #[test]
+    fn steep_returns_still_cross_the_court() {
+        // A ball already falling fast, struck at the very end of the paddle.
+        let returned = limit_bounce_angle(paddle_return(Vec2::new(-600.0, -900.0), 0.5));
+        let speed = paddle_return(Vec2::new(-600.0, -900.0), 0.5).magnitude();
+
+        assert!(returned.x > 0.0);
+        assert!(returned.x >= speed * MAX_BOUNCE_ANGLE.to_radians().cos() - 1e-3);
+        assert!((returned.magnitude() - speed).abs() < 1e-2);
+
+        let shallow = Vec2::new(600.0, 100.0);
+        assert_eq!(limit_bounce_angle(shallow), shallow);
+    }
+
+    #[test]
+    fn corner_hits_spin_no_harder_than_end_hits() {
+        let end = paddle_return(Vec2::new(-600.0, 0.0), MAX_HIT_OFFSET);
+        let extreme = paddle_return(Vec2::new(-600.0, 0.0), 3.0);
+        assert_eq!(extreme, end);
+        assert!(extreme.y.abs() <= PADDLE_SPIN * MAX_HIT_OFFSET * (1.0 + EDGE_SPIN_BONUS));
+
+        // A ball whose centre is past the paddle's end is measured at the end.
+        let paddle = Rectangle::new(16.0, 400.0, 32.0, 160.0);
+        let clipping = Rectangle::new(30.0, 370.0, 24.0, 24.0);
+        assert_eq!(hit_offset(clipping, paddle), MAX_HIT_OFFSET);
+    }
+
+    #[test]
+    fn taller_paddle_gives_gentler_spin() {
+        let ball = Rectangle::new(30.0, 400.0, 24.0, 24.0);
+        let paddle = Rectangle::new(16.0, 400.0, 32.0, 160.0);
+        let tall_paddle = Rectangle::new(16.0, 360.0, 32.0, 240.0);
+
+        let offset = hit_offset(ball, paddle);
+        let tall_offset = hit_offset(ball, tall_paddle);
+
+        assert!((offset - 0.425).abs() < 1e-5);
+        assert!((tall_offset - 0.2833).abs() < 1e-4);
+        assert!(
+            paddle_return(Vec2::new(-600.0, 0.0), tall_offset).y
+                > paddle_return(Vec2::new(-600.0, 0.0), offset).y
+        );
+    }
+
+    #[test]
+    fn options_stay_valid_and_survive_a_save() {
+        let mut settings = Settings {
+            win_score: MIN_WIN_SCORE,
+            ball_speed: MAX_BALL_SPEED,
+            ..Settings::default()
+        };
+
+        adjust_setting(&mut settings, OptionItem::WinScore, -1);
+        adjust_setting(&mut settings, OptionItem::BallSpeed, 1);
+        adjust_setting(&mut settings, OptionItem::Sound, 1);
+        assert_eq!(settings.win_score, MIN_WIN_SCORE);
+        assert_eq!(settings.ball_speed, MAX_BALL_SPEED);
+        assert!(!settings.sound);
+        assert!(settings.validate().is_ok());
+
+        adjust_setting(&mut settings, OptionItem::WinScore, 1);
+        adjust_setting(&mut settings, OptionItem::BallSpeed, -1);
+        assert_eq!(settings.win_score, MIN_WIN_SCORE + 1);
+        assert_eq!(settings.ball_speed, MAX_BALL_SPEED - BALL_SPEED_STEP);
+
+        let saved = toml::to_string_pretty(&settings).unwrap();
+        assert_eq!(Settings::parse(&saved).unwrap(), settings);
+    }
+
+    #[test]
+    fn settings_reject_invalid_handicaps() {
+        let slow = Settings::parse("player2_paddle_speed = 0.0").unwrap();
+        assert!(slow.validate().is_err());
+
+        let tiny = Settings::parse("player1_paddle_size = -1.0").unwrap();
+        assert!(tiny.validate().is_err());
+
+        let handicap =
+            Settings::parse("player1_paddle_speed = 300.0\nplayer2_paddle_size = 1.5").unwrap();
+        assert!(handicap.validate().is_ok());
+        assert_eq!(handicap.player1_paddle_speed, Some(300.0));
+        assert_eq!(handicap.player2_paddle_speed, None);
+    }
+
+    #[test]
+    fn game_needs_a_two_point_lead() {
+        assert_eq!(game_status(3, 2, 11), GameStatus::InPlay);
+        assert_eq!(game_status(10, 8, 11), GameStatus::MatchPoint(Side::Left));
+        assert_eq!(game_status(11, 8, 11), GameStatus::Won(Side::Left));
+        assert_eq!(game_status(11, 10, 11), GameStatus::MatchPoint(Side::Left));
+        assert_eq!(game_status(9, 11, 11), GameStatus::Won(Side::Right));
+    }
+
+    #[test]
+    fn deuce_and_advantage_alternate_until_someone_leads_by_two() {
+        assert_eq!(game_status(10, 10, 11), GameStatus::Deuce);
+        assert_eq!(game_status(10, 11, 11), GameStatus::MatchPoint(Side::Right));
+        assert_eq!(game_status(11, 11, 11), GameStatus::Deuce);
+        assert_eq!(game_status(12, 11, 11), GameStatus::MatchPoint(Side::Left));
+        assert_eq!(game_status(13, 11, 11), GameStatus::Won(Side::Left));
+        assert_eq!(GameStatus::Deuce.label(), "DEUCE");
+        assert_eq!(GameStatus::MatchPoint(Side::Right).label(), "MATCH POINT");
+    }
+
+    #[test]
+    fn net_reddens_towards_match_point() {
+        assert_eq!(net_tension(3, 2, 11), 0.0);
+        assert_eq!(net_tension(4, 8, 11), 0.5);
+        assert_eq!(net_tension(10, 9, 11), 1.0);
+        assert_eq!(net_tension(0, 0, 1), 1.0);
+    }
+
+    #[test]
+    fn settings_reject_invalid_values() {
+        let negative_speed = Settings::parse("ball_speed = -10.0").unwrap();
+        assert!(negative_speed.validate().is_err());
+
+        let zero_score = Settings::parse("win_score = 0").unwrap();
+        assert!(zero_score.validate().is_err());
+
+        let one_point = Settings::parse("win_score = 1").unwrap();
+        assert!(one_point.validate().is_err());
+
+        let loud = Settings::parse("master_volume = 1.5").unwrap();
+        assert!(loud.validate().is_err());
+
+        assert!(Settings::parse("ball_speed = \"fast\"").is_err());
+    }
+
+    #[test]
+    fn volume_steps_in_tenths_and_stays_in_range() {
+        let mut volume = 1.0;
+        for _ in 0..3 {
+            volume = step_volume(volume, -1);
+        }
+        assert_eq!(volume, 0.7);
+
+        assert_eq!(step_volume(0.0, -1), 0.0);
+        assert_eq!(step_volume(1.0, 1), 1.0);
+    }
+
+    #[test]
+    fn timer_message_rounds_up_to_whole_seconds() {
+        assert_eq!(timer_message(120.0), "02:00");
+        assert_eq!(timer_message(59.2), "01:00");
+        assert_eq!(timer_message(0.4), "00:01");
+        assert_eq!(timer_message(-1.0), "00:00");
+    }
+
+    #[test]
+    fn records_fill_missing_fields_and_reject_garbage() {
+        let records = Records::parse(r#"{ "longest_rally": 12 }"#).unwrap();
+        assert_eq!(
+            records,
+            Records {
+                longest_rally: 12,
+                ..Records::default()
+            }
+        );
+
+        assert!(Records::parse("not json").is_err());
+    }
+
+    #[test]
+    fn game_over_message_lists_the_stats() {
+        let stats = MatchStats {
+            hits_p1: 14,
+            hits_p2: 9,
+            duration: 75.0,
+            max_ball_speed: 1234.4,
+        };
+        let message = game_over_message("Player 1 wins!", &stats, 6);
+
+        assert!(message.starts_with("Player 1 wins!\n"));
+        assert!(message.contains("Hits: 14 - 9"));
+        assert!(message.contains("Longest rally: 6"));
+        assert!(message.contains("Match time: 01:15"));
+        assert!(message.contains("Top speed: 1234"));
+        assert!(message.ends_with("Press Enter to Restart or Esc to quit game"));
+    }
+
+    #[test]
+    fn serve_speed_scales_with_charge() {
+        assert_eq!(serve_speed(BALL_SPEED, 0.0), BALL_SPEED);
+        assert_eq!(serve_speed(BALL_SPEED, 1.0), BALL_SPEED * SERVE_MAX_POWER);
+        assert_eq!(serve_speed(BALL_SPEED, 2.0), BALL_SPEED * SERVE_MAX_POWER);
+    }
+
+    #[test]
+    fn paddle_keys_direction() {
+        let keys = Controls::default().player1;
+
+        assert_eq!(keys.direction(|_| false), 0.0);
+        assert_eq!(keys.direction(|key| key == Key::W), -1.0);
+        assert_eq!(keys.direction(|key| key == Key::S), 1.0);
+        assert_eq!(keys.direction(|key| key == Key::W || key == Key::S), 0.0);
+        assert_eq!(keys.direction(|key| key == Key::Up), 0.0);
+    }
+
+    #[test]
+    fn random_serve_stays_within_cone() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let max_slope = SERVE_MAX_ANGLE.to_radians().tan();
+
+        for _ in 0..100 {
+            let velocity = random_serve_velocity(&mut rng, BALL_SPEED);
+
+            assert!((velocity.magnitude() - BALL_SPEED).abs() < 1e-2);
+            assert!(velocity.y.abs() <= velocity.x.abs() * max_slope + 1e-3);
+        }
+    }
+
+    #[test]
+    fn random_serve_is_deterministic_for_a_seed() {
+        let mut a = StdRng::seed_from_u64(42);
+        let mut b = StdRng::seed_from_u64(42);
+
+        for _ in 0..10 {
+            assert_eq!(
+                random_serve_velocity(&mut a, BALL_SPEED),
+                random_serve_velocity(&mut b, BALL_SPEED)
+            );
+        }
+    }
+
+    #[test]
+    fn near_miss_only_counts_narrow_gaps() {
+        let paddle = Rectangle::new(16.0, 100.0, 24.0, 104.0);
+        let ball = |y: f32| Rectangle::new(10.0, y, 22.0, 22.0);
+
+        assert!(is_near_miss(ball(210.0), paddle, NEAR_MISS_MARGIN));
+        assert!(is_near_miss(ball(60.0), paddle, NEAR_MISS_MARGIN));
+        assert!(!is_near_miss(ball(150.0), paddle, NEAR_MISS_MARGIN));
+        assert!(!is_near_miss(ball(400.0), paddle, NEAR_MISS_MARGIN));
+    }
+
+    #[test]
+    fn obstacle_bounce_reflects_on_the_shallow_axis() {
+        let obstacle = Rectangle::new(100.0, 100.0, 40.0, 200.0);
+
+        let (position, velocity) = obstacle_bounce(
+            Rectangle::new(85.0, 180.0, 22.0, 22.0),
+            Vec2::new(600.0, 50.0),
+            obstacle,
+            0.0,
+        )
+        .expect("ball entering the left face should bounce");
+        assert_eq!(position, Vec2::new(78.0, 180.0));
+        assert_eq!(velocity, Vec2::new(-600.0, 50.0));
+
+        let (position, velocity) = obstacle_bounce(
+            Rectangle::new(110.0, 285.0, 22.0, 22.0),
+            Vec2::new(100.0, -400.0),
+            obstacle,
+            0.0,
+        )
+        .expect("ball entering the bottom face should bounce");
+        assert_eq!(position, Vec2::new(110.0, 300.0));
+        assert_eq!(velocity, Vec2::new(100.0, 400.0));
+
+        // Already leaving, so no second bounce.
+        assert!(obstacle_bounce(
+            Rectangle::new(85.0, 180.0, 22.0, 22.0),
+            Vec2::new(-600.0, 0.0),
+            obstacle,
+            0.0,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn moving_obstacle_imparts_its_velocity() {
+        let obstacle = Rectangle::new(100.0, 100.0, 40.0, 200.0);
+
+        let (_, velocity) = obstacle_bounce(
+            Rectangle::new(85.0, 180.0, 22.0, 22.0),
+            Vec2::new(600.0, 0.0),
+            obstacle,
+            200.0,
+        )
+        .unwrap();
+        assert_eq!(velocity, Vec2::new(-600.0, 200.0 * OBSTACLE_SPIN));
+
+        // Caught by the top face while falling more slowly than it rises.
+        let (_, velocity) = obstacle_bounce(
+            Rectangle::new(110.0, 85.0, 22.0, 22.0),
+            Vec2::new(0.0, 100.0),
+            obstacle,
+            -200.0,
+        )
+        .unwrap();
+        assert_eq!(velocity, Vec2::new(0.0, -500.0));
+    }
+
+    #[test]
+    fn bricks_fill_the_centred_grid() {
+        let grid = brick_grid(1920.0, 1080.0);
+        let bricks = build_bricks(1920.0, 1080.0);
+
+        assert_eq!(bricks.len(), BRICK_COLUMNS * BRICK_ROWS);
+        assert_eq!(bricks[0].bounds.x, grid.x);
+        assert_eq!(bricks[0].bounds.y, grid.y);
+
+        let last = bricks.last().unwrap().bounds;
+        assert_eq!(last.x + last.width, grid.x + grid.width);
+        assert_eq!(last.y + last.height, grid.y + grid.height);
+        assert!((grid.center().x - 960.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn centre_hits_are_fast_and_edge_hits_spin() {
+        let incoming = Vec2::new(600.0, 100.0);
+
+        let centre = paddle_return(incoming, 0.0);
+        assert_eq!(centre.x, -(600.0 + BALL_ACC) * SWEET_SPOT_SPEEDUP);
+        assert_eq!(centre.y, 100.0 * SWEET_SPOT_STRAIGHTEN);
+
+        let edge = paddle_return(incoming, -0.5);
+        assert_eq!(edge.x, -(600.0 + BALL_ACC) * EDGE_SPEED_FACTOR);
+        assert!(edge.y > centre.y + PADDLE_SPIN / 2.0);
+
+        // Mirrored for the other paddle.
+        let mirrored = paddle_return(Vec2::new(-600.0, 100.0), -0.5);
+        assert_eq!(mirrored.x, -edge.x);
+        assert_eq!(mirrored.y, edge.y);
+    }
+
+    #[test]
+    fn predict_intercept_follows_wall_bounces() {
+        let predict = |y: f32, vy: f32| {
+            predict_intercept_y(
+                Vec2::new(100.0, y),
+                Vec2::new(500.0, vy),
+                20.0,
+                600.0,
+                520.0,
+            )
+        };
+
+        // One second of travel in each case; the usable range is 0..500.
+        assert_eq!(predict(200.0, 100.0), Some(300.0));
+        assert_eq!(predict(400.0, 300.0), Some(300.0));
+        assert_eq!(predict(100.0, -300.0), Some(200.0));
+        assert_eq!(predict(0.0, 1100.0), Some(100.0));
+
+        assert_eq!(
+            predict_intercept_y(
+                Vec2::new(100.0, 200.0),
+                Vec2::new(-500.0, 0.0),
+                20.0,
+                600.0,
+                520.0
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn ai_controller_settles_on_target() {
+        let mut ai = AiController::default();
+        let dt = 1.0 / 60.0;
+        let mut y = 0.0;
+
+        for _ in 0..180 {
+            let velocity = ai.step(300.0 - y, dt, PADDLE_SPEED);
+            assert!(velocity.abs() <= PADDLE_SPEED);
+            y += velocity * dt;
+        }
+
+        assert!((y - 300.0).abs() < 2.0, "settled at {}", y);
+    }
+
+    #[test]
+    fn hitstop_scales_with_impact_speed() {
+        assert_eq!(hitstop_frames(BALL_SPEED), 0);
+        assert_eq!(hitstop_frames(HITSTOP_MIN_SPEED), HITSTOP_MIN_FRAMES);
+        assert_eq!(hitstop_frames(MAX_BALL_SPEED), HITSTOP_MAX_FRAMES);
+        assert_eq!(hitstop_frames(MAX_BALL_SPEED * 2.0), HITSTOP_MAX_FRAMES);
+    }
+
+    #[test]
+    fn shields_sit_between_the_goal_lines_and_the_paddles() {
+        let court = Vec2::new(1920.0, 1080.0);
+        let left = shield_bounds(Side::Left, court);
+        let right = shield_bounds(Side::Right, court);
+
+        assert!(left.x > 0.0 && left.right() < PADDLE_MARGIN);
+        assert!(right.x > court.x - PADDLE_MARGIN && right.right() < court.x);
+        assert!((left.center().y - court.y / 2.0).abs() < 1e-3);
+        assert!(left.height < court.y && left.height > court.y * 0.5);
+    }
+
+    #[test]
+    fn spun_ball_curves_and_keeps_its_speed() {
+        let start = Vec2::new(600.0, 0.0);
+        let spin = hit_spin(0.0, PADDLE_SPEED, start.x);
+        assert!(spin > 0.0);
+
+        let (mut straight, mut curved) = (Vec2::<f32>::zero(), Vec2::<f32>::zero());
+        let (mut velocity, mut remaining) = (start, spin);
+        for _ in 0..60 {
+            straight += start / 60.0;
+            (velocity, remaining) = apply_magnus(velocity, remaining, 1.0 / 60.0);
+            curved += velocity / 60.0;
+        }
+
+        // A paddle moving down as it strikes bends the ball down the screen.
+        assert!(curved.y > straight.y + 10.0);
+        assert!((velocity.magnitude() - start.magnitude()).abs() < 1e-2);
+        assert!(remaining < spin);
+
+        // The same stroke returned the other way still curves down.
+        assert!(hit_spin(0.0, PADDLE_SPEED, -start.x) < 0.0);
+        let (returned, _) = apply_magnus(-start, -spin, 0.5);
+        assert!(returned.y > 0.0);
+    }
+
+    #[test]
+    fn spin_decays_without_slowing_the_ball_across() {
+        let mut velocity = Vec2::new(-600.0, 300.0);
+
+        for _ in 0..60 {
+            velocity = decay_spin(velocity, 1.0 / 60.0);
+        }
+
+        assert_eq!(velocity.x, -600.0);
+        assert!(velocity.y > 0.0 && velocity.y < 300.0);
+        assert!((velocity.y - 300.0 * (-SPIN_DECAY).exp()).abs() < 0.01);
+    }
+
+    #[test]
+    fn clamp_speed_keeps_direction() {
+        let clamped = clamp_speed(Vec2::new(3000.0, -4000.0), 1000.0);
+
+        assert!((clamped.magnitude() - 1000.0).abs() < 1e-3);
+        assert!((clamped.x - 600.0).abs() < 1e-3);
+        assert!((clamped.y + 800.0).abs() < 1e-3);
+        assert_eq!(
+            clamp_speed(Vec2::new(300.0, 0.0), 1000.0),
+            Vec2::new(300.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn sweep_catches_fast_ball_that_skips_the_paddle() {
+        let paddle = Rectangle::new(16.0, 400.0, 32.0, 160.0);
+        let ball_size = Vec2::new(24.0, 24.0);
+        let from = Vec2::new(80.0, 460.0);
+        let to = Vec2::new(-20.0, 460.0);
+
+        assert!(!Rectangle::new(to.x, to.y, ball_size.x, ball_size.y).intersects(&paddle));
+
+        let t = sweep(from, to, ball_size, paddle).expect("swept path crosses the paddle");
+        assert!((t - 0.32).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sweep_catches_fast_diagonal_ball() {
+        let paddle = Rectangle::new(1872.0, 400.0, 32.0, 160.0);
+        let ball_size = Vec2::new(24.0, 24.0);
+        let from = Vec2::new(1800.0, 380.0);
+        let to = Vec2::new(1950.0, 530.0);
+
+        assert!(sweep(from, to, ball_size, paddle).is_some());
+    }
+
+    #[test]
+    fn sweep_misses_ball_passing_above_the_paddle() {
+        let paddle = Rectangle::new(16.0, 400.0, 32.0, 160.0);
+        let ball_size = Vec2::new(24.0, 24.0);
+        let from = Vec2::new(80.0, 300.0);
+        let to = Vec2::new(-20.0, 300.0);
+
+        assert_eq!(sweep(from, to, ball_size, paddle), None);
+    }
+
+    #[test]
+    fn deep_overlap_pushes_ball_out_of_right_paddle() {
+        let paddle = Rectangle::new(1872.0, 400.0, 32.0, 160.0);
+        let ball_width = 24.0;
+
+        let x = paddle_contact(ball_width, 600.0, paddle, Side::Right)
+            .expect("ball moving into the paddle should hit it");
+
+        assert!(x + ball_width <= paddle.x);
+        assert_eq!(
+            paddle_contact(ball_width, -600.0, paddle, Side::Right),
+            None
+        );
+    }
+
+    #[test]
+    fn playback_feeds_back_the_recorded_frames() {
+        let frames: Vec<FrameInput> = (0..90)
+            .map(|frame| FrameInput {
+                player1: ((frame / 45) % 3) as f32 - 1.0,
+                player2: ((frame / 70) % 3) as f32 - 1.0,
+                ..FrameInput::default()
+            })
+            .collect();
+        let recording = Replay {
+            seed: 42,
+            four_player: false,
+            player2_control: ControlMode::Human,
+            extra_control: ControlMode::Human,
+            difficulty: Difficulty::Medium,
+            court: Court::Open,
+            match_mode: MatchMode::Points,
+            orientation: Orientation::Horizontal,
+            gravity: true,
+            catch_assist: true,
+            settings: ReplaySettings {
+                ball_speed: 1200.0,
+                win_score: 5,
+                player2_paddle_size: 0.5,
+                ..ReplaySettings::default()
+            },
+            frames: frames.clone(),
+        };
+
+        let json = serde_json::to_string(&recording).unwrap();
+        let replay: Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(replay.seed, 42);
+
+        let mut playback = Playback::new(replay, Settings::default());
+        let played_by = playback.settings();
+        assert!(played_by.gravity && played_by.catch_assist);
+        assert_eq!(played_by.ball_speed, 1200.0);
+        assert_eq!(played_by.win_score, 5);
+        assert_eq!(played_by.player2_paddle_size, 0.5);
+        assert_eq!(played_by.paddle_speed, PADDLE_SPEED);
+        assert_eq!(playback.live_settings, Settings::default());
+        let played: Vec<FrameInput> = std::iter::from_fn(|| playback.next_input()).collect();
+        assert_eq!(played, frames);
+        assert_eq!(playback.next_input(), None);
+    }
+
+    // A match on a fixed court, sized as the game sizes it with the built-in
+    // textures.
+    fn simulation(settings: Settings) -> Simulation {
+        let side = Vec2::new(32.0, 160.0);
+        let end = Vec2::new(160.0, 32.0);
+        Simulation::new(
+            settings,
+            Vec2::new(1280.0, 720.0),
+            [side, side, end, end],
+            Vec2::broadcast(24.0),
+            StdRng::seed_from_u64(0),
+        )
+    }
+
+    // Steps through `inputs` until they run out or the match is over, and
+    // returns the score it ends on.
+    fn play_out(sim: &mut Simulation, inputs: impl Iterator<Item = FrameInput>) -> (u32, u32) {
+        for input in inputs {
+            let events = sim.step(&input, 1.0 / TICK_RATE as f32);
+
+            if events
+                .iter()
+                .any(|event| matches!(event, SimEvent::MatchOver(_)))
+            {
+                break;
+            }
+        }
+
+        (sim.player1_score, sim.player2_score)
+    }
+
+    #[test]
+    fn replaying_a_match_ends_on_the_same_score() {
+        let settings = Settings::default();
+        let mut recording = Replay {
+            seed: 42,
+            four_player: false,
+            player2_control: ControlMode::Human,
+            extra_control: ControlMode::Human,
+            difficulty: Difficulty::Medium,
+            court: Court::Open,
+            match_mode: MatchMode::Points,
+            orientation: Orientation::Horizontal,
+            gravity: settings.gravity,
+            catch_assist: settings.catch_assist,
+            settings: ReplaySettings::of(&settings),
+            frames: Vec::new(),
+        };
+
+        // A minute of both players sweeping up and down blind, out of step
+        // with each other, so where the serves go decides who misses.
+        let mut sim = simulation(settings);
+        sim.start(recording.seed);
+        let scripted = (0..3600).map(|tick| FrameInput {
+            player1: ((tick / 50) % 3) as f32 - 1.0,
+            player2: ((tick / 70) % 3) as f32 - 1.0,
+            serve1: tick % 300 < 20,
+            dash: [tick % 240 == 0, tick % 330 == 0, false, false],
+            ..FrameInput::default()
+        });
+        let recorded = play_out(
+            &mut sim,
+            scripted.inspect(|&input| recording.frames.push(input)),
+        );
+        assert_ne!(recorded, (0, 0));
+
+        let json = serde_json::to_string(&recording).unwrap();
+        let replay: Replay = serde_json::from_str(&json).unwrap();
+        let mut playback = Playback::new(replay, Settings::default());
+
+        let mut sim = simulation(playback.settings());
+        sim.start(playback.replay.seed);
+        let played = play_out(&mut sim, std::iter::from_fn(|| playback.next_input()));
+        assert_eq!(played, recorded);
+    }
+
+    #[test]
+    fn replays_without_settings_play_by_the_defaults() {
+        let recording = Replay {
+            seed: 7,
+            four_player: false,
+            player2_control: ControlMode::Human,
+            extra_control: ControlMode::Human,
+            difficulty: Difficulty::Medium,
+            court: Court::Open,
+            match_mode: MatchMode::Points,
+            orientation: Orientation::Horizontal,
+            gravity: false,
+            catch_assist: false,
+            settings: ReplaySettings::default(),
+            frames: Vec::new(),
+        };
+        let mut json = serde_json::to_value(&recording).unwrap();
+        json.as_object_mut().unwrap().remove("settings");
+
+        let replay: Replay = serde_json::from_value(json).unwrap();
+        assert_eq!(replay.settings, ReplaySettings::of(&Settings::default()));
+    }
+
+    #[test]
+    fn toasts_show_one_at_a_time_until_they_expire() {
+        let mut toasts = VecDeque::from([("first".to_owned(), 1.0), ("second".to_owned(), 1.0)]);
+
+        advance_toasts(&mut toasts, 0.6);
+        assert_eq!(toasts.len(), 2);
+        assert_eq!(toasts[1].1, 1.0);
+
+        advance_toasts(&mut toasts, 0.6);
+        assert_eq!(
+            toasts.front().map(|(message, _)| message.as_str()),
+            Some("second")
+        );
+
+        advance_toasts(&mut toasts, 1.0);
+        assert!(toasts.is_empty());
+        advance_toasts(&mut toasts, 1.0);
+    }
+
+    #[test]
+    fn only_a_winner_gets_a_paddle_to_celebrate() {
+        assert_eq!(winner_index("Player 1"), Some(0));
+        assert_eq!(winner_index("Player 4"), Some(3));
+        assert_eq!(winner_index("Draw"), None);
+    }
+
+    #[test]
+    fn celebration_swells_and_settles_back() {
+        assert_eq!(celebration_paddle_scale(0.0), 1.0);
+        assert!(celebration_paddle_scale(CELEBRATION_DURATION / 2.0) > 1.0);
+        assert_eq!(celebration_paddle_scale(CELEBRATION_DURATION), 1.0);
+
+        assert_eq!(celebration_text_scale(0.0), 0.0);
+        assert!(celebration_text_scale(CELEBRATION_TEXT_TIME / 2.0) > 0.5);
+        assert_eq!(celebration_text_scale(CELEBRATION_TEXT_TIME), 1.0);
+    }
+}
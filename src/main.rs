@@ -1,194 +1,224 @@
-use tetra::graphics::text::{Font, Text};
-use tetra::graphics::{self, Color, Rectangle, Texture};
-use tetra::input::{self, Key};
-use tetra::math::Vec2;
-use tetra::window::{self, get_height, get_width};
-use tetra::{Context, ContextBuilder, State};
-
-const WINDOW_WIDTH: f32 = 1920.0;
-const WINDOW_HEIGHT: f32 = 1080.0;
-const PADDLE_SPEED: f32 = 8.0;
-const BALL_SPEED: f32 = 10.0;
-const PADDLE_SPIN: f32 = 4.0;
-const BALL_ACC: f32 = 0.5;
+mod config;
+mod entity;
+mod game;
 
-fn main() -> tetra::Result {
-    ContextBuilder::new("Pong", WINDOW_WIDTH as i32, WINDOW_HEIGHT as i32)
-        .quit_on_escape(true)
-        .high_dpi(true)
-        .fullscreen(true)
-        .build()?
-        .run(GameState::new)
-}
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
-struct Entity {
-    texture: Texture,
-    position: Vec2<f32>,
-    velocity: Vec2<f32>,
-}
+use tetra::time::Timestep;
+use tetra::ContextBuilder;
 
-impl Entity {
-    fn new(texture: Texture, position: Vec2<f32>) -> Entity {
-        Entity::with_velocity(texture, position, Vec2::zero())
-    }
+use crate::config::{SETTINGS_PATH, TICK_RATE};
+use crate::game::{EventLog, GameState, NetLink, NetRole, Settings};
 
-    fn with_velocity(texture: Texture, position: Vec2<f32>, velocity: Vec2<f32>) -> Entity {
-        Entity {
-            texture,
-            position,
-            velocity,
+fn main() -> tetra::Result {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
         }
-    }
-
-    fn width(&self) -> f32 {
-        self.texture.width() as f32
-    }
+    };
 
-    fn height(&self) -> f32 {
-        self.texture.height() as f32
-    }
+    let mut settings = match Settings::load(SETTINGS_PATH) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Invalid settings in {}: {}", SETTINGS_PATH, e);
+            std::process::exit(1);
+        }
+    };
 
-    fn bounds(&self) -> Rectangle {
-        Rectangle::new(
-            self.position.x,
-            self.position.y,
-            self.width(),
-            self.height(),
-        )
-    }
+    args.apply(&mut settings);
 
-    fn centre(&self) -> Vec2<f32> {
-        Vec2::new(
-            self.position.x + (self.width() / 2.0),
-            self.position.y + (self.height() / 2.0),
-        )
+    if let Err(e) = settings.validate() {
+        eprintln!("{}\n{}", e, USAGE);
+        std::process::exit(2);
     }
-}
 
-struct GameState {
-    player1: Entity,
-    player2: Entity,
-    ball: Entity,
-    winner: String,
-}
+    let seed = args.seed;
+    let net = match (args.host, args.connect, args.spectate) {
+        (Some(port), _, _) => Some(NetLink::host(port)),
+        (None, Some(address), _) => Some(NetLink::connect(address, NetRole::Client)),
+        (None, None, Some(address)) => Some(NetLink::connect(address, NetRole::Spectator)),
+        (None, None, None) => None,
+    };
+    let net = match net.transpose() {
+        Ok(net) => net,
+        Err(e) => {
+            eprintln!("Network error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-impl GameState {
-    fn new(ctx: &mut Context) -> tetra::Result<GameState> {
-        let player1_texture = Texture::new(ctx, "./img/player1.png")?;
-        let player1_position = Vec2::new(
-            16.0,
-            (get_height(ctx) as f32 - player1_texture.height() as f32) / 2.0,
-        );
+    let event_log = match args.log_events.as_deref().map(EventLog::open).transpose() {
+        Ok(event_log) => event_log,
+        Err(e) => {
+            eprintln!("Could not open the event log: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-        let player2_texture = Texture::new(ctx, "./img/player2.png")?;
-        let player2_position = Vec2::new(
-            get_width(ctx) as f32 - player2_texture.width() as f32 - 16.0,
-            (get_height(ctx) as f32 - player2_texture.height() as f32) / 2.0,
-        );
+    ContextBuilder::new("Pong", settings.window_width, settings.window_height)
+        .high_dpi(true)
+        .fullscreen(settings.fullscreen)
+        .timestep(Timestep::Fixed(TICK_RATE))
+        .build()?
+        .run(|ctx| {
+            let mut state = GameState::new(ctx, seed, settings)?;
+            state.event_log = event_log;
 
-        let ball_texture = Texture::new(ctx, "./img/ball.png")?;
-        let ball_position = Vec2::new(
-            get_width(ctx) as f32 / 2.0 - ball_texture.width() as f32 / 2.0,
-            get_height(ctx) as f32 / 2.0 - ball_texture.height() as f32 / 2.0,
-        );
-        let ball_velocity = Vec2::new(-BALL_SPEED, 0.0);
+            if let Some(link) = net {
+                state.start_network_match(ctx, link);
+            }
 
-        Ok(GameState {
-            player1: Entity::new(player1_texture, player1_position),
-            player2: Entity::new(player2_texture, player2_position),
-            ball: Entity::with_velocity(ball_texture, ball_position, ball_velocity),
-            winner: String::new(),
+            Ok(state)
         })
-    }
 }
 
-impl State for GameState {
-    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
-        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
-
-        self.player1.texture.draw(ctx, self.player1.position);
-        self.player2.texture.draw(ctx, self.player2.position);
-        self.ball.texture.draw(ctx, self.ball.position);
-
-        Ok(())
-    }
+const USAGE: &str =
+    "Usage: pong-tetra-rust [--seed <u64>] [--width <px>] [--height <px>] [--windowed | --fullscreen] \
+     [--host <port> | --connect <ip:port> | --spectate <ip:port>] [--log-events <file>]";
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+struct Args {
+    seed: Option<u64>,
+    width: Option<i32>,
+    height: Option<i32>,
+    fullscreen: Option<bool>,
+    // Network play, as the host on this port or the client of that address.
+    host: Option<u16>,
+    connect: Option<SocketAddr>,
+    // Or watching the match hosted at that address.
+    spectate: Option<SocketAddr>,
+    // A CSV file to append ball events to.
+    log_events: Option<PathBuf>,
+}
 
-    fn update(&mut self, ctx: &mut Context) -> tetra::Result {
-        if input::is_key_down(ctx, Key::W) {
-            self.player1.position.y -= PADDLE_SPEED;
+impl Args {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+        let mut parsed = Args::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => parsed.seed = Some(parse_value(&arg, args.next())?),
+                "--width" => parsed.width = Some(parse_value(&arg, args.next())?),
+                "--height" => parsed.height = Some(parse_value(&arg, args.next())?),
+                "--windowed" => parsed.fullscreen = Some(false),
+                "--fullscreen" => parsed.fullscreen = Some(true),
+                "--host" => parsed.host = Some(parse_value(&arg, args.next())?),
+                "--connect" => parsed.connect = Some(parse_value(&arg, args.next())?),
+                "--spectate" => parsed.spectate = Some(parse_value(&arg, args.next())?),
+                "--log-events" => parsed.log_events = Some(parse_value(&arg, args.next())?),
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
         }
 
-        if input::is_key_down(ctx, Key::S) {
-            self.player1.position.y += PADDLE_SPEED;
+        let roles = [
+            parsed.host.is_some(),
+            parsed.connect.is_some(),
+            parsed.spectate.is_some(),
+        ];
+        if roles.into_iter().filter(|&role| role).count() > 1 {
+            return Err("Only one of --host, --connect and --spectate can be used".to_string());
         }
 
-        if input::is_key_down(ctx, Key::Up) {
-            self.player2.position.y -= PADDLE_SPEED;
-        }
+        Ok(parsed)
+    }
 
-        if input::is_key_down(ctx, Key::Down) {
-            self.player2.position.y += PADDLE_SPEED;
+    fn apply(&self, settings: &mut Settings) {
+        if let Some(width) = self.width {
+            settings.window_width = width;
         }
 
-        self.ball.position += self.ball.velocity;
-
-        let player1_bounds = self.player1.bounds();
-        let player2_bounds = self.player2.bounds();
-        let ball_bounds = self.ball.bounds();
+        if let Some(height) = self.height {
+            settings.window_height = height;
+        }
 
-        let paddle_hit = if ball_bounds.intersects(&player1_bounds) {
-            Some(&self.player1)
-        } else if ball_bounds.intersects(&player2_bounds) {
-            Some(&self.player2)
-        } else {
-            None
-        };
+        if let Some(fullscreen) = self.fullscreen {
+            settings.fullscreen = fullscreen;
+        }
+    }
+}
 
-        if let Some(paddle) = paddle_hit {
-            self.ball.velocity.x =
-                -(self.ball.velocity.x + (BALL_ACC * self.ball.velocity.x.signum()));
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
 
-            let offset = (paddle.centre().y - self.ball.centre().y) / paddle.height();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WINDOW_HEIGHT;
 
-            self.ball.velocity.y += PADDLE_SPIN * -offset;
-        }
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
 
-        if self.ball.position.y <= 0.0
-            || self.ball.position.y + self.ball.height() >= get_height(ctx) as f32
-        {
-            self.ball.velocity.y = -self.ball.velocity.y;
-        }
+    #[test]
+    fn args_parse_optional_values() {
+        assert_eq!(Args::parse(args(&[])), Ok(Args::default()));
+        assert_eq!(
+            Args::parse(args(&["--seed", "1234"])).map(|a| a.seed),
+            Ok(Some(1234))
+        );
+        assert_eq!(
+            Args::parse(args(&["--width", "1280", "--height", "720", "--windowed"])),
+            Ok(Args {
+                seed: None,
+                width: Some(1280),
+                height: Some(720),
+                fullscreen: Some(false),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            Args::parse(args(&["--log-events", "events.csv"])).map(|a| a.log_events),
+            Ok(Some(PathBuf::from("events.csv")))
+        );
+        assert!(Args::parse(args(&["--seed"])).is_err());
+        assert!(Args::parse(args(&["--seed", "abc"])).is_err());
+        assert!(Args::parse(args(&["--bogus"])).is_err());
+    }
 
-        if self.ball.position.x > WINDOW_WIDTH {
-            self.winner = "Player 1".to_string();
-        } else if self.ball.position.x < 0.0 {
-            self.winner = "Player 2".to_string();
-        }
+    #[test]
+    fn args_pick_one_network_role() {
+        assert_eq!(
+            Args::parse(args(&["--host", "7777"])).map(|a| a.host),
+            Ok(Some(7777))
+        );
+        assert_eq!(
+            Args::parse(args(&["--connect", "127.0.0.1:7777"])).map(|a| a.connect),
+            Ok(Some("127.0.0.1:7777".parse().unwrap()))
+        );
+        assert!(Args::parse(args(&["--connect", "7777"])).is_err());
+        assert_eq!(
+            Args::parse(args(&["--spectate", "127.0.0.1:7777"])).map(|a| a.spectate),
+            Ok(Some("127.0.0.1:7777".parse().unwrap()))
+        );
+        assert!(Args::parse(args(&["--host", "1", "--connect", "127.0.0.1:1"])).is_err());
+        assert!(Args::parse(args(&[
+            "--connect",
+            "127.0.0.1:1",
+            "--spectate",
+            "127.0.0.1:1"
+        ]))
+        .is_err());
+    }
 
-        if !self.winner.is_empty() {
-            self.winner
-                .push_str(" wins!\nPress Enter to Restart or Esc to quit game");
-            let mut winner_text = Text::new(
-                self.winner.to_string(),
-                Font::vector(ctx, "./fonts/wheaton.otf", 32.0)?,
-            );
-            let text_position = Vec2::new(
-                get_width(ctx) as f32 / 2.0 - 400.0,
-                get_height(ctx) as f32 / 2.0 - 100.0,
-            );
-
-            winner_text.draw(ctx, text_position);
-
-            if input::is_key_down(ctx, Key::Enter) {
-                self.winner = String::new();
-                self.ball.position = Vec2::new(
-                    get_width(ctx) as f32 / 2.0 - self.ball.texture.width() as f32 / 2.0,
-                    get_height(ctx) as f32 / 2.0 - self.ball.texture.height() as f32 / 2.0,
-                );
-                self.ball.velocity = Vec2::new(-BALL_SPEED, 0.0);
-            }
-        }
+    #[test]
+    fn args_override_settings() {
+        let mut settings = Settings::default();
+        Args::parse(args(&["--width", "1280", "--windowed"]))
+            .unwrap()
+            .apply(&mut settings);
 
-        Ok(())
+        assert_eq!(settings.window_width, 1280);
+        assert_eq!(settings.window_height, WINDOW_HEIGHT as i32);
+        assert!(!settings.fullscreen);
     }
 }